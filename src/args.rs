@@ -1,12 +1,133 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::ffi::CString;
 
 use crate::errors;
+use crate::formatters::{LongListing, SortKey, Template};
+use crate::load::{DataSource, SourcePaths};
+use crate::responses::OutputFormat;
 
 pub use errors::Error;
 
+// How an OBJECT given on the command line is matched against user or group records
+#[derive(Clone)]
+pub enum ObjectName {
+    // Matched by name only
+    Name(String),
+    // Matched by numeric ID only
+    Id(i64),
+    // An all-digit OBJECT, matched by name if such a record exists and by numeric ID otherwise
+    NameOrId(String, i64),
+}
+
+impl ObjectName {
+    pub fn find<'a, T>(
+        &self,
+        records: &'a [T],
+        name_of: fn(&T) -> &str,
+        id_of: fn(&T) -> i64,
+    ) -> Option<&'a T> {
+        match self {
+            ObjectName::Name(name) => records.iter().find(|r| name_of(r) == name),
+            ObjectName::Id(id) => records.iter().find(|r| id_of(r) == *id),
+            ObjectName::NameOrId(name, id) => records
+                .iter()
+                .find(|r| name_of(r) == name)
+                .or_else(|| records.iter().find(|r| id_of(r) == *id)),
+        }
+    }
+
+    // Describes the object for error messages, e.g. `alice` or `ID 1000`
+    pub fn describe(&self) -> String {
+        match self {
+            ObjectName::Name(name) | ObjectName::NameOrId(name, _) => name.clone(),
+            ObjectName::Id(id) => format!("ID {}", id),
+        }
+    }
+}
+
+// What to list when no OBJECT is given
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Listing {
+    Groups,
+    Users,
+    // Groups, each with their users nested underneath
+    GroupTree,
+    // Users, each with their groups nested underneath
+    UserTree,
+    // Users by groups, with a cell for each membership
+    Matrix,
+}
+
+// Which memberships to list
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MembershipFilter {
+    All,
+    // Memberships by primary group ID, whether or not the user is also listed as a member
+    PrimaryOnly,
+    // Memberships by being listed as a member, whether or not it is also the primary group
+    SupplementaryOnly,
+}
+
+// Narrows down which users and memberships are listed
+#[derive(Clone, Copy)]
+pub struct Filters {
+    pub login_shell_only: bool,
+    pub membership: MembershipFilter,
+}
+
+// Narrows down the rows and columns of --matrix to the users and groups whose names match
+// any of the given shell globs. No globs match every name.
+pub struct MatrixFilter {
+    pub user_globs: Vec<String>,
+    pub group_globs: Vec<String>,
+}
+
+// Matched with fnmatch(3), as the shell would match file names
+fn matches_glob(glob: &str, name: &str) -> bool {
+    match (CString::new(glob), CString::new(name)) {
+        (Ok(glob), Ok(name)) => unsafe { libc::fnmatch(glob.as_ptr(), name.as_ptr(), 0) == 0 },
+        _ => false,
+    }
+}
+
+impl MatrixFilter {
+    pub fn matches_user(&self, name: &str) -> bool {
+        return self.user_globs.is_empty() || self.user_globs.iter().any(|g| matches_glob(g, name));
+    }
+
+    pub fn matches_group(&self, name: &str) -> bool {
+        return self.group_globs.is_empty()
+            || self.group_globs.iter().any(|g| matches_glob(g, name));
+    }
+}
+
+// A mode named by the first positional argument, instead of an OBJECT
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Subcommand {
+    // Checks the records for consistency problems
+    Audit,
+    // Runs CIS benchmark style checks, reporting whether each passed
+    Compliance,
+    // Writes an HTML access report, with --html
+    Report,
+}
+
 pub struct TargetObjects {
-    pub user_to_list: Option<String>,
-    pub group_to_list: Option<String>,
+    pub users_to_list: Vec<ObjectName>,
+    pub groups_to_list: Vec<ObjectName>,
+    // Whether to report one result per object, even if there is only one
+    pub batch: bool,
+    // A user and a group to check the membership of, instead of listing anything
+    pub membership_to_check: Option<(ObjectName, ObjectName)>,
+    pub listing: Listing,
+    pub subcommand: Option<Subcommand>,
+    pub source: DataSource,
+    pub filters: Filters,
+    // Whether to report the file and line that each membership comes from
+    pub explain: bool,
+    // Whether to list the members of each group in the group listing, for -l
+    pub list_members: bool,
+    pub matrix_filter: MatrixFilter,
 }
 
 #[derive(PartialEq, Eq, Hash)]
@@ -15,68 +136,446 @@ pub enum FlagArg {
     HELP,
     USER,
     GROUP,
+    UID,
+    GID,
+    BYNAME,
+    USERS,
+    TREE,
+    BYUSER,
+    STDIN,
+    ISMEMBER,
+    QUIET,
+    PRIMARYONLY,
+    SUPPLEMENTARYONLY,
+    SHELLLOGINONLY,
+    EXPLAIN,
+    STRICT,
+    LONG,
+    REVERSE,
+    MATRIX,
+}
+
+// Options that take a value, e.g. `--root /mnt/image` or `--root=/mnt/image`
+#[derive(PartialEq, Eq, Hash)]
+pub enum OptionArg {
+    ROOT,
+    PASSWDFILE,
+    GROUPFILE,
+    GSHADOWFILE,
+    SOURCE,
+    FORMAT,
+    TEMPLATE,
+    COLUMNS,
+    SORT,
+    HTML,
+    MATCHUSERS,
+    MATCHGROUPS,
 }
 
 pub fn process_args(
     flag_args: HashSet<FlagArg>,
+    option_args: HashMap<OptionArg, String>,
     pos_args: Vec<String>,
-) -> Result<TargetObjects, Box<dyn Error>> {
-    if pos_args.len() > 1 {
+) -> Result<TargetObjects, Error> {
+    let source = process_source_args(&option_args)?;
+    let filters = Filters {
+        login_shell_only: flag_args.contains(&FlagArg::SHELLLOGINONLY),
+        membership: match (
+            flag_args.contains(&FlagArg::PRIMARYONLY),
+            flag_args.contains(&FlagArg::SUPPLEMENTARYONLY),
+        ) {
+            (false, false) => MembershipFilter::All,
+            (true, false) => MembershipFilter::PrimaryOnly,
+            (false, true) => MembershipFilter::SupplementaryOnly,
+            (true, true) => {
+                return Err(errors::usage_error(
+                    "The --primary-only and --supplementary-only options cannot be combined"
+                        .to_string(),
+                ));
+            }
+        },
+    };
+
+    let by_name = flag_args.contains(&FlagArg::BYNAME);
+    let from_stdin = flag_args.contains(&FlagArg::STDIN);
+    let listing = if flag_args.contains(&FlagArg::MATRIX) {
+        if flag_args.contains(&FlagArg::TREE) || flag_args.contains(&FlagArg::USERS) {
+            return Err(errors::usage_error(
+                "The --matrix option cannot be combined with --users or --tree".to_string(),
+            ));
+        }
+        Listing::Matrix
+    } else if flag_args.contains(&FlagArg::TREE) {
+        if flag_args.contains(&FlagArg::BYUSER) {
+            Listing::UserTree
+        } else {
+            Listing::GroupTree
+        }
+    } else if flag_args.contains(&FlagArg::USERS) {
+        Listing::Users
+    } else {
+        Listing::Groups
+    };
+
+    if listing != Listing::Groups && (!pos_args.is_empty() || from_stdin) {
         return Err(errors::usage_error(
-            "Too many positional arguments (expected at most 1)".to_string(),
+            "The --users, --tree and --matrix listings do not take an OBJECT".to_string(),
         ));
     }
 
-    let first_arg = pos_args.iter().next();
+    if flag_args.contains(&FlagArg::BYUSER) && !flag_args.contains(&FlagArg::TREE) {
+        return Err(errors::usage_error(
+            "The --by-user option requires --tree".to_string(),
+        ));
+    }
 
-    if flag_args.contains(&FlagArg::USER) {
-        match first_arg {
-            None => {
-                return Err(errors::usage_error(
-                    "Missing required argument OBJECT".to_string(),
-                ));
-            }
-            Some(user_name) => {
-                return Ok(TargetObjects {
-                    user_to_list: Some(user_name.to_string()),
-                    group_to_list: None,
-                });
-            }
+    let glob_list = |option: OptionArg| match option_args.get(&option) {
+        Some(globs) => globs
+            .split(',')
+            .filter(|glob| !glob.is_empty())
+            .map(str::to_string)
+            .collect(),
+        None => vec![],
+    };
+    let matrix_filter = MatrixFilter {
+        user_globs: glob_list(OptionArg::MATCHUSERS),
+        group_globs: glob_list(OptionArg::MATCHGROUPS),
+    };
+    let matches_options = option_args.contains_key(&OptionArg::MATCHUSERS)
+        || option_args.contains_key(&OptionArg::MATCHGROUPS);
+    if matches_options && listing != Listing::Matrix {
+        return Err(errors::usage_error(
+            "The --match-users and --match-groups options require --matrix".to_string(),
+        ));
+    }
+
+    let mut target_objects = TargetObjects {
+        users_to_list: vec![],
+        groups_to_list: vec![],
+        batch: from_stdin || pos_args.len() > 1,
+        membership_to_check: None,
+        listing: listing,
+        subcommand: None,
+        source: source,
+        filters: filters,
+        explain: flag_args.contains(&FlagArg::EXPLAIN),
+        list_members: flag_args.contains(&FlagArg::LONG) && listing == Listing::Groups,
+        matrix_filter: matrix_filter,
+    };
+
+    if flag_args.contains(&FlagArg::ISMEMBER) {
+        let object_flags = [FlagArg::USER, FlagArg::UID, FlagArg::GROUP, FlagArg::GID];
+        if from_stdin || object_flags.iter().any(|flag| flag_args.contains(flag)) {
+            return Err(errors::usage_error(
+                "The --is-member check cannot be combined with -u, -g or --stdin".to_string(),
+            ));
         }
-    } else if flag_args.contains(&FlagArg::GROUP) {
-        match first_arg {
-            None => {
-                return Err(errors::usage_error(
-                    "Missing required argument OBJECT".to_string(),
-                ));
-            }
-            Some(group_name) => {
-                return Ok(TargetObjects {
-                    user_to_list: None,
-                    group_to_list: Some(group_name.to_string()),
-                });
-            }
+        if pos_args.len() != 2 {
+            return Err(errors::usage_error(
+                "The --is-member check expects exactly two OBJECTs (a user and a group)"
+                    .to_string(),
+            ));
         }
-    }
 
-    if let Some(object_name) = first_arg {
+        target_objects.batch = false;
+        target_objects.membership_to_check = Some((
+            process_object_name(&pos_args[0], false, by_name, "user ID")?,
+            process_object_name(&pos_args[1], false, by_name, "group ID")?,
+        ));
+    } else if flag_args.contains(&FlagArg::USER) || flag_args.contains(&FlagArg::UID) {
+        if pos_args.is_empty() && !from_stdin {
+            return Err(errors::usage_error(
+                "Missing required argument OBJECT".to_string(),
+            ));
+        }
+
+        let by_id = flag_args.contains(&FlagArg::UID);
+        for user_name in pos_args.iter() {
+            target_objects
+                .users_to_list
+                .push(process_object_name(user_name, by_id, by_name, "user ID")?);
+        }
+    } else if flag_args.contains(&FlagArg::GROUP) || flag_args.contains(&FlagArg::GID) {
+        if pos_args.is_empty() && !from_stdin {
+            return Err(errors::usage_error(
+                "Missing required argument OBJECT".to_string(),
+            ));
+        }
+
+        let by_id = flag_args.contains(&FlagArg::GID);
+        for group_name in pos_args.iter() {
+            target_objects
+                .groups_to_list
+                .push(process_object_name(group_name, by_id, by_name, "group ID")?);
+        }
+    } else if let Some(subcommand) = pos_args.first().and_then(|arg| process_subcommand(arg)) {
+        if pos_args.len() > 1 || from_stdin || listing != Listing::Groups {
+            return Err(errors::usage_error(format!(
+                "The `{}` subcommand does not take an OBJECT or listing options",
+                pos_args[0]
+            )));
+        }
+        target_objects.batch = false;
+        target_objects.subcommand = Some(subcommand);
+    } else if let Some(object_name) = pos_args.first() {
         return Err(errors::usage_error(
             format!(
                 "Cannot list object of name `{}`; not specified as user or group. Use the `-u` or `-g` flag to specify",
                 object_name
             )
         ));
+    } else if from_stdin {
+        return Err(errors::usage_error(
+            "Reading objects from --stdin requires the `-u` or `-g` flag".to_string(),
+        ));
+    }
+
+    let is_listing = target_objects.users_to_list.is_empty()
+        && target_objects.groups_to_list.is_empty()
+        && target_objects.membership_to_check.is_none()
+        && target_objects.subcommand.is_none();
+    let is_long_listing = listing == Listing::Groups || listing == Listing::Users;
+    if flag_args.contains(&FlagArg::LONG) && !(is_listing && is_long_listing) {
+        return Err(errors::usage_error(
+            "The -l option only applies to the group and user listings".to_string(),
+        ));
     }
 
-    return Ok(TargetObjects {
-        user_to_list: None,
-        group_to_list: None,
+    let is_report = target_objects.subcommand == Some(Subcommand::Report);
+    if is_report != option_args.contains_key(&OptionArg::HTML) {
+        return Err(errors::usage_error(
+            "The `report` subcommand and the --html option require each other".to_string(),
+        ));
+    }
+
+    // The graph formats draw the memberships of every group, as listed by the tree views
+    let format = option_args.get(&OptionArg::FORMAT).map(String::as_str);
+    if is_listing && (format == Some("dot") || format == Some("mermaid")) {
+        target_objects.listing = match listing {
+            Listing::Groups => Listing::GroupTree,
+            Listing::Users => Listing::UserTree,
+            tree => tree,
+        };
+    }
+
+    return Ok(target_objects);
+}
+
+fn process_subcommand(arg: &str) -> Option<Subcommand> {
+    match arg {
+        "audit" => Some(Subcommand::Audit),
+        "compliance" => Some(Subcommand::Compliance),
+        "report" => Some(Subcommand::Report),
+        _ => None,
+    }
+}
+
+// All-digit objects are matched by ID as a fallback, unless `by_name` turns that off
+fn process_object_name(
+    object: &str,
+    by_id: bool,
+    by_name: bool,
+    id_description: &'static str,
+) -> Result<ObjectName, Error> {
+    let parsed_id = object.parse::<i64>();
+
+    if by_id {
+        return parsed_id
+            .map(ObjectName::Id)
+            .map_err(|_| errors::usage_error(format!("Invalid {} `{}`", id_description, object)));
+    }
+
+    match parsed_id {
+        Ok(id) if !by_name && object.chars().all(|c| c.is_ascii_digit()) => {
+            Ok(ObjectName::NameOrId(object.to_string(), id))
+        }
+        _ => Ok(ObjectName::Name(object.to_string())),
+    }
+}
+
+// `--json` is shorthand for `--format json`
+pub fn process_format_args(
+    flag_args: &HashSet<FlagArg>,
+    option_args: &HashMap<OptionArg, String>,
+) -> Result<OutputFormat, Error> {
+    let format = match option_args.get(&OptionArg::FORMAT).map(String::as_str) {
+        None => {
+            if flag_args.contains(&FlagArg::JSON) {
+                OutputFormat::Json
+            } else {
+                OutputFormat::Text
+            }
+        }
+        Some(name) => match OutputFormat::from_name(name) {
+            Some(format) => format,
+            None => {
+                return Err(errors::usage_error(format!(
+                    "Unknown format `{}` (expected one of: {})",
+                    name,
+                    OutputFormat::NAMES.join(", ")
+                )));
+            }
+        },
+    };
+
+    if flag_args.contains(&FlagArg::JSON) && format != OutputFormat::Json {
+        return Err(errors::usage_error(
+            "The --json option cannot be combined with another --format".to_string(),
+        ));
+    }
+
+    let long_options = option_args.contains_key(&OptionArg::COLUMNS)
+        || option_args.contains_key(&OptionArg::SORT)
+        || flag_args.contains(&FlagArg::REVERSE);
+    if long_options && !flag_args.contains(&FlagArg::LONG) {
+        return Err(errors::usage_error(
+            "The --columns, --sort and --reverse options require -l".to_string(),
+        ));
+    }
+
+    if let Some(path) = option_args.get(&OptionArg::HTML) {
+        if format != OutputFormat::Text
+            || flag_args.contains(&FlagArg::LONG)
+            || option_args.contains_key(&OptionArg::TEMPLATE)
+        {
+            return Err(errors::usage_error(
+                "The --html option cannot be combined with --json, --format, --template or -l"
+                    .to_string(),
+            ));
+        }
+        return Ok(OutputFormat::Html(path.to_string()));
+    }
+
+    if flag_args.contains(&FlagArg::LONG) {
+        if format != OutputFormat::Text || option_args.contains_key(&OptionArg::TEMPLATE) {
+            return Err(errors::usage_error(
+                "The -l option cannot be combined with --json, --format or --template".to_string(),
+            ));
+        }
+        return Ok(OutputFormat::Long(process_long_listing_args(
+            flag_args,
+            option_args,
+        )?));
+    }
+
+    if let Some(template) = option_args.get(&OptionArg::TEMPLATE) {
+        if format != OutputFormat::Text {
+            return Err(errors::usage_error(
+                "The --template option cannot be combined with --json or --format".to_string(),
+            ));
+        }
+        return Ok(OutputFormat::Template(Template::parse(template)?));
+    }
+
+    return Ok(format);
+}
+
+fn process_long_listing_args(
+    flag_args: &HashSet<FlagArg>,
+    option_args: &HashMap<OptionArg, String>,
+) -> Result<LongListing, Error> {
+    let columns = option_args.get(&OptionArg::COLUMNS).map(|columns| {
+        columns
+            .split(',')
+            .map(|column| column.trim().to_string())
+            .filter(|column| !column.is_empty())
+            .collect::<Vec<String>>()
     });
+    if columns.as_ref().is_some_and(|columns| columns.is_empty()) {
+        return Err(errors::usage_error(
+            "The --columns option expects at least one column".to_string(),
+        ));
+    }
+
+    let sort = match option_args.get(&OptionArg::SORT) {
+        None => None,
+        Some(name) => match SortKey::from_name(name) {
+            Some(key) => Some(key),
+            None => {
+                return Err(errors::usage_error(format!(
+                    "Unknown sort key `{}` (expected `name`, `id` or `members`)",
+                    name
+                )));
+            }
+        },
+    };
+
+    return Ok(LongListing {
+        columns: columns,
+        sort: sort,
+        reverse: flag_args.contains(&FlagArg::REVERSE),
+    });
+}
+
+// The output format asked for by `args`, made out without parsing them in full, so that
+// errors in parsing them can be reported in that format. --json wins over a conflicting
+// --format, and an unknown --format is reported as text.
+pub fn requested_format(args: &[String]) -> OutputFormat {
+    let mut format = OutputFormat::Text;
+    let mut opt_args = args.iter().take_while(|arg| *arg != "--");
+    while let Some(arg) = opt_args.next() {
+        let value = match arg.strip_prefix("--format=") {
+            Some(value) => Some(value),
+            None if arg == "--format" => opt_args.next().map(String::as_str),
+            None => None,
+        };
+        if let Some(requested) = value.and_then(OutputFormat::from_name) {
+            format = requested;
+        }
+    }
+
+    if args
+        .iter()
+        .take_while(|arg| *arg != "--")
+        .any(|arg| arg == "--json")
+    {
+        return OutputFormat::Json;
+    }
+    return format;
 }
 
-pub fn parse_argv_data(
-    args: Vec<String>,
-) -> Result<(HashSet<FlagArg>, Vec<String>), Box<dyn Error>> {
+fn process_source_args(option_args: &HashMap<OptionArg, String>) -> Result<DataSource, Error> {
+    let root = option_args.get(&OptionArg::ROOT);
+    let passwd_file = option_args.get(&OptionArg::PASSWDFILE);
+    let group_file = option_args.get(&OptionArg::GROUPFILE);
+    let gshadow_file = option_args.get(&OptionArg::GSHADOWFILE);
+
+    let source_paths = || {
+        SourcePaths::new(
+            root.map(String::as_str),
+            passwd_file.map(String::as_str),
+            group_file.map(String::as_str),
+            gshadow_file.map(String::as_str),
+        )
+    };
+
+    match option_args.get(&OptionArg::SOURCE).map(String::as_str) {
+        None | Some("files") => Ok(DataSource::Files(source_paths())),
+        Some("userdb") => Ok(DataSource::userdb(source_paths())),
+        Some("nss") => {
+            if root.is_some()
+                || passwd_file.is_some()
+                || group_file.is_some()
+                || gshadow_file.is_some()
+            {
+                return Err(errors::usage_error(
+                    "The `nss` source cannot be combined with --root or explicit file paths"
+                        .to_string(),
+                ));
+            }
+            Ok(DataSource::Nss)
+        }
+        Some(other) => Err(errors::usage_error(format!(
+            "Unknown source `{}` (expected `files`, `userdb` or `nss`)",
+            other
+        ))),
+    }
+}
+
+// Flags, options with their values, and positional arguments
+type ArgvData = (HashSet<FlagArg>, HashMap<OptionArg, String>, Vec<String>);
+
+pub fn parse_argv_data(args: Vec<String>) -> Result<ArgvData, Error> {
     let double_hyphen_pos = args.iter().position(|x| x == "--");
     let opt_args = {
         match double_hyphen_pos {
@@ -85,8 +584,43 @@ pub fn parse_argv_data(
         }
     };
 
-    let valid_short_flags = vec!["-u", "-g"];
-    let valid_long_flags = vec!["--json", "--help", "--user", "--group"];
+    let valid_short_flags = ["-u", "-g", "-l"];
+    let valid_long_flags = vec![
+        "--json",
+        "--help",
+        "--user",
+        "--group",
+        "--uid",
+        "--gid",
+        "--by-name",
+        "--users",
+        "--tree",
+        "--by-user",
+        "--stdin",
+        "--is-member",
+        "--quiet",
+        "--primary-only",
+        "--supplementary-only",
+        "--shell-login-only",
+        "--explain",
+        "--strict",
+        "--reverse",
+        "--matrix",
+    ];
+    let valid_option_flags = [
+        "--root",
+        "--passwd-file",
+        "--group-file",
+        "--gshadow-file",
+        "--source",
+        "--format",
+        "--template",
+        "--columns",
+        "--sort",
+        "--html",
+        "--match-users",
+        "--match-groups",
+    ];
     let valid_long_flags_iter = valid_long_flags.iter().map(|x| String::from(*x).clone());
     let valid_short_flags_iter = valid_short_flags.iter().map(|x| String::from(*x).clone());
 
@@ -99,14 +633,66 @@ pub fn parse_argv_data(
     };
 
     let mut flag_args: HashSet<FlagArg> = HashSet::new();
+    let mut option_args: HashMap<OptionArg, String> = HashMap::new();
     let mut unrecognized_flags: HashSet<String> = HashSet::new();
     let mut positional_args: Vec<String> = vec![];
 
-    let opt_args_as_strings = opt_args.iter().map(|x| x.to_string());
+    let mut opt_args_as_strings = opt_args.iter().map(|x| x.to_string());
 
-    for opt_arg in opt_args_as_strings {
+    while let Some(opt_arg) = opt_args_as_strings.next() {
         if opt_arg.clone().starts_with("-") {
-            if matches_long_flag(opt_arg.clone()) {
+            let (option_name, inline_value) = match opt_arg.find('=') {
+                Some(pos) if opt_arg.starts_with("--") => (
+                    opt_arg[..pos].to_string(),
+                    Some(opt_arg[pos + 1..].to_string()),
+                ),
+                _ => (opt_arg.clone(), None),
+            };
+
+            if valid_option_flags.contains(&option_name.as_str()) {
+                let value = match inline_value.or_else(|| opt_args_as_strings.next()) {
+                    Some(value) => value,
+                    None => {
+                        return Err(errors::usage_error(format!(
+                            "Missing value for option {}",
+                            option_name
+                        )));
+                    }
+                };
+
+                let option = if option_name == "--root" {
+                    OptionArg::ROOT
+                } else if option_name == "--passwd-file" {
+                    OptionArg::PASSWDFILE
+                } else if option_name == "--group-file" {
+                    OptionArg::GROUPFILE
+                } else if option_name == "--gshadow-file" {
+                    OptionArg::GSHADOWFILE
+                } else if option_name == "--source" {
+                    OptionArg::SOURCE
+                } else if option_name == "--format" {
+                    OptionArg::FORMAT
+                } else if option_name == "--template" {
+                    OptionArg::TEMPLATE
+                } else if option_name == "--columns" {
+                    OptionArg::COLUMNS
+                } else if option_name == "--sort" {
+                    OptionArg::SORT
+                } else if option_name == "--html" {
+                    OptionArg::HTML
+                } else if option_name == "--match-users" {
+                    OptionArg::MATCHUSERS
+                } else if option_name == "--match-groups" {
+                    OptionArg::MATCHGROUPS
+                } else {
+                    return Err(errors::internal_error(format!(
+                        "unknown option {}",
+                        option_name
+                    )));
+                };
+
+                option_args.insert(option, value);
+            } else if matches_long_flag(opt_arg.clone()) {
                 if opt_arg == "--json" {
                     flag_args.insert(FlagArg::JSON);
                 } else if opt_arg == "--help" {
@@ -115,6 +701,38 @@ pub fn parse_argv_data(
                     flag_args.insert(FlagArg::USER);
                 } else if opt_arg == "--group" {
                     flag_args.insert(FlagArg::GROUP);
+                } else if opt_arg == "--uid" {
+                    flag_args.insert(FlagArg::UID);
+                } else if opt_arg == "--gid" {
+                    flag_args.insert(FlagArg::GID);
+                } else if opt_arg == "--by-name" {
+                    flag_args.insert(FlagArg::BYNAME);
+                } else if opt_arg == "--users" {
+                    flag_args.insert(FlagArg::USERS);
+                } else if opt_arg == "--tree" {
+                    flag_args.insert(FlagArg::TREE);
+                } else if opt_arg == "--by-user" {
+                    flag_args.insert(FlagArg::BYUSER);
+                } else if opt_arg == "--stdin" {
+                    flag_args.insert(FlagArg::STDIN);
+                } else if opt_arg == "--is-member" {
+                    flag_args.insert(FlagArg::ISMEMBER);
+                } else if opt_arg == "--quiet" {
+                    flag_args.insert(FlagArg::QUIET);
+                } else if opt_arg == "--primary-only" {
+                    flag_args.insert(FlagArg::PRIMARYONLY);
+                } else if opt_arg == "--supplementary-only" {
+                    flag_args.insert(FlagArg::SUPPLEMENTARYONLY);
+                } else if opt_arg == "--shell-login-only" {
+                    flag_args.insert(FlagArg::SHELLLOGINONLY);
+                } else if opt_arg == "--explain" {
+                    flag_args.insert(FlagArg::EXPLAIN);
+                } else if opt_arg == "--strict" {
+                    flag_args.insert(FlagArg::STRICT);
+                } else if opt_arg == "--reverse" {
+                    flag_args.insert(FlagArg::REVERSE);
+                } else if opt_arg == "--matrix" {
+                    flag_args.insert(FlagArg::MATRIX);
                 } else {
                     return Err(errors::internal_error(format!(
                         "unknown long flag {}",
@@ -126,6 +744,8 @@ pub fn parse_argv_data(
                     flag_args.insert(FlagArg::USER);
                 } else if opt_arg == "-g" {
                     flag_args.insert(FlagArg::GROUP);
+                } else if opt_arg == "-l" {
+                    flag_args.insert(FlagArg::LONG);
                 } else {
                     return Err(errors::internal_error(format!(
                         "unknown long flag {}",
@@ -145,12 +765,12 @@ pub fn parse_argv_data(
             // As a special case, we ignore unrecognized flags if we can
             // find a --help thrown somewhere in there. This is similar
             // to how some other CLI utilities work.
-            return Ok((flag_args, vec![]));
+            return Ok((flag_args, option_args, vec![]));
         } else {
-            return Err(Box::new(errors::usage_error(format!(
+            return Err(errors::usage_error(format!(
                 "Unrecognized flag {}",
                 unrecognized_flag
-            ))));
+            )));
         }
     }
 
@@ -161,5 +781,5 @@ pub fn parse_argv_data(
 
     positional_args.extend_from_slice(trailing_pos_args);
 
-    return Ok((flag_args, positional_args));
+    return Ok((flag_args, option_args, positional_args));
 }
@@ -0,0 +1,155 @@
+// Contains the consistency checks run by `groupls audit` over the loaded user and group records
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::records::{GroupEntry, PasswdEntry};
+use crate::responses::{Finding, Location, Severity};
+
+// Groups `records` that share a key, in order of first appearance, leaving out unique keys
+fn duplicates<'a, T, K: Eq + Hash + Clone>(
+    records: &'a [T],
+    key_of: fn(&T) -> K,
+) -> Vec<(K, Vec<&'a T>)> {
+    let mut positions: HashMap<K, usize> = HashMap::new();
+    let mut sets: Vec<(K, Vec<&'a T>)> = vec![];
+    for record in records {
+        let key = key_of(record);
+        match positions.get(&key) {
+            Some(position) => sets[*position].1.push(record),
+            None => {
+                positions.insert(key.clone(), sets.len());
+                sets.push((key, vec![record]));
+            }
+        }
+    }
+    return sets.into_iter().filter(|(_, set)| set.len() > 1).collect();
+}
+
+fn user_names(users: &[&PasswdEntry]) -> String {
+    let names: Vec<&str> = users.iter().map(|user| user.user.as_str()).collect();
+    return names.join(", ");
+}
+
+fn group_names(groups: &[&GroupEntry]) -> String {
+    let names: Vec<&str> = groups.iter().map(|group| group.group.as_str()).collect();
+    return names.join(", ");
+}
+
+fn user_locations(users: &[&PasswdEntry]) -> Vec<Location> {
+    return users
+        .iter()
+        .map(|user| Location::of(&user.provenance))
+        .collect();
+}
+
+fn group_locations(groups: &[&GroupEntry]) -> Vec<Location> {
+    return groups
+        .iter()
+        .map(|group| Location::of(&group.provenance))
+        .collect();
+}
+
+// Runs every check, returning the findings of each check in turn
+pub fn audit(users: &[PasswdEntry], groups: &[GroupEntry]) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = vec![];
+
+    for (name, set) in duplicates(users, |u| u.user.clone()) {
+        findings.push(Finding {
+            id: "duplicate-user-name".to_string(),
+            severity: Severity::Error,
+            message: format!("User name `{}` is defined {} times", name, set.len()),
+            locations: user_locations(&set),
+        });
+    }
+
+    // Accounts sharing UID 0 are reported by the `multiple-root-accounts` check instead
+    for (id, set) in duplicates(users, |u| u.user_id) {
+        if id == 0 {
+            continue;
+        }
+        findings.push(Finding {
+            id: "duplicate-uid".to_string(),
+            severity: Severity::Error,
+            message: format!("UID {} is shared by {}", id, user_names(&set)),
+            locations: user_locations(&set),
+        });
+    }
+
+    for (name, set) in duplicates(groups, |g| g.group.clone()) {
+        findings.push(Finding {
+            id: "duplicate-group-name".to_string(),
+            severity: Severity::Error,
+            message: format!("Group name `{}` is defined {} times", name, set.len()),
+            locations: group_locations(&set),
+        });
+    }
+
+    for (id, set) in duplicates(groups, |g| g.group_id) {
+        findings.push(Finding {
+            id: "duplicate-gid".to_string(),
+            severity: Severity::Error,
+            message: format!("GID {} is shared by {}", id, group_names(&set)),
+            locations: group_locations(&set),
+        });
+    }
+
+    for user in users {
+        if !groups.iter().any(|g| g.group_id == user.primary_group_id) {
+            findings.push(Finding {
+                id: "missing-primary-group".to_string(),
+                severity: Severity::Warning,
+                message: format!(
+                    "User `{}` has primary GID {}, which no group carries",
+                    user.user, user.primary_group_id
+                ),
+                locations: vec![Location::of(&user.provenance)],
+            });
+        }
+    }
+
+    for group in groups {
+        for username in group.usernames.iter() {
+            if !users.iter().any(|u| u.user == *username) {
+                findings.push(Finding {
+                    id: "unknown-member".to_string(),
+                    severity: Severity::Warning,
+                    message: format!(
+                        "Group `{}` lists `{}` as a member, but no such user exists",
+                        group.group, username
+                    ),
+                    locations: vec![Location::of(group.member_provenance(username))],
+                });
+            }
+        }
+    }
+
+    for group in groups {
+        let has_members = !group.usernames.is_empty()
+            || users.iter().any(|u| u.primary_group_id == group.group_id);
+        if !has_members {
+            findings.push(Finding {
+                id: "empty-group".to_string(),
+                severity: Severity::Info,
+                message: format!("Group `{}` has no members", group.group),
+                locations: vec![Location::of(&group.provenance)],
+            });
+        }
+    }
+
+    let root_accounts: Vec<&PasswdEntry> = users.iter().filter(|u| u.user_id == 0).collect();
+    if root_accounts.len() > 1 {
+        findings.push(Finding {
+            id: "multiple-root-accounts".to_string(),
+            severity: Severity::Error,
+            message: format!(
+                "{} accounts have UID 0: {}",
+                root_accounts.len(),
+                user_names(&root_accounts)
+            ),
+            locations: user_locations(&root_accounts),
+        });
+    }
+
+    return findings;
+}
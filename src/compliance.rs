@@ -0,0 +1,152 @@
+// Contains the CIS benchmark style user and group checks run by `groupls compliance`.
+// Checks are titled after the benchmark's recommendations, whose numbering varies between
+// benchmark versions and distributions.
+
+use crate::audit;
+use crate::records::{GroupEntry, PasswdEntry, Provenance};
+use crate::responses::{CheckStatus, ComplianceCheck, Finding, Location, Severity};
+
+const ROOT_USER: &'static str = "root";
+const SHADOW_GROUP: &'static str = "shadow";
+
+// A failure found by a check: what is wrong, and where
+type Failure = (String, Vec<Location>);
+
+// A check passes if it has no failures, or is skipped if it could not be run
+fn compliance_check(
+    id: &'static str,
+    title: &'static str,
+    remediation: &'static str,
+    failures: Option<Vec<Failure>>,
+) -> ComplianceCheck {
+    let status = match &failures {
+        None => CheckStatus::Skipped,
+        Some(failures) if failures.is_empty() => CheckStatus::Pass,
+        Some(_) => CheckStatus::Fail,
+    };
+
+    return ComplianceCheck {
+        id: id.to_string(),
+        title: title.to_string(),
+        status: status,
+        remediation: remediation.to_string(),
+        findings: failures
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(message, locations)| Finding {
+                id: id.to_string(),
+                severity: Severity::Error,
+                message: message,
+                locations: locations,
+            })
+            .collect(),
+    };
+}
+
+// The audit findings of the given kinds, restated as failures
+fn audit_failures(audit_findings: &[Finding], audit_ids: &[&str]) -> Vec<Failure> {
+    return audit_findings
+        .iter()
+        .filter(|finding| audit_ids.contains(&finding.id.as_str()))
+        .map(|finding| (finding.message.clone(), finding.locations.clone()))
+        .collect();
+}
+
+// Runs every check. Legacy entries are None if the data source has no files to look for them in.
+pub fn check(
+    users: &[PasswdEntry],
+    groups: &[GroupEntry],
+    legacy_entries: Option<Vec<Provenance>>,
+) -> Vec<ComplianceCheck> {
+    let audit_findings = audit::audit(users, groups);
+
+    let root_failures = users
+        .iter()
+        .filter(|user| user.user_id == 0 && user.user != ROOT_USER)
+        .map(|user| {
+            (
+                format!("User `{}` has UID 0", user.user),
+                vec![Location::of(&user.provenance)],
+            )
+        })
+        .collect();
+
+    let mut shadow_failures = vec![];
+    for group in groups.iter().filter(|group| group.group == SHADOW_GROUP) {
+        for username in group.usernames.iter() {
+            shadow_failures.push((
+                format!("User `{}` is a member of the shadow group", username),
+                vec![Location::of(group.member_provenance(username))],
+            ));
+        }
+        for user in users.iter() {
+            if user.primary_group_id == group.group_id {
+                shadow_failures.push((
+                    format!("User `{}` has the shadow group as primary group", user.user),
+                    vec![Location::of(&user.provenance)],
+                ));
+            }
+        }
+    }
+
+    let legacy_failures = legacy_entries.map(|entries| {
+        entries
+            .iter()
+            .map(|entry| ("Legacy `+` entry".to_string(), vec![Location::of(entry)]))
+            .collect()
+    });
+
+    return vec![
+        compliance_check(
+            "passwd-groups-exist",
+            "Ensure all groups in /etc/passwd exist in /etc/group",
+            "Create the missing groups with groupadd, or change the primary group of the users with usermod -g",
+            Some(audit_failures(&audit_findings, &["missing-primary-group"])),
+        ),
+        compliance_check(
+            "no-duplicate-uids",
+            "Ensure no duplicate UIDs exist",
+            "Give each user a unique UID with usermod -u, and update the ownership of their files",
+            Some(audit_failures(
+                &audit_findings,
+                &["duplicate-uid", "multiple-root-accounts"],
+            )),
+        ),
+        compliance_check(
+            "no-duplicate-gids",
+            "Ensure no duplicate GIDs exist",
+            "Give each group a unique GID with groupmod -g, and update the ownership of their files",
+            Some(audit_failures(&audit_findings, &["duplicate-gid"])),
+        ),
+        compliance_check(
+            "no-duplicate-user-names",
+            "Ensure no duplicate user names exist",
+            "Rename the duplicate users with usermod -l, or remove them with userdel",
+            Some(audit_failures(&audit_findings, &["duplicate-user-name"])),
+        ),
+        compliance_check(
+            "no-duplicate-group-names",
+            "Ensure no duplicate group names exist",
+            "Rename the duplicate groups with groupmod -n, or remove them with groupdel",
+            Some(audit_failures(&audit_findings, &["duplicate-group-name"])),
+        ),
+        compliance_check(
+            "root-only-uid-0",
+            "Ensure root is the only UID 0 account",
+            "Remove the other accounts with userdel, or give them a non-zero UID with usermod -u",
+            Some(root_failures),
+        ),
+        compliance_check(
+            "shadow-group-empty",
+            "Ensure shadow group is empty",
+            "Remove the members of the shadow group with gpasswd -d, and change the primary group of its users with usermod -g",
+            Some(shadow_failures),
+        ),
+        compliance_check(
+            "no-legacy-entries",
+            "Ensure no legacy \"+\" entries exist in /etc/passwd and /etc/group",
+            "Remove the `+` entries, and configure NIS in /etc/nsswitch.conf if it is still needed",
+            legacy_failures,
+        ),
+    ];
+}
@@ -1,33 +1,171 @@
-use std::fmt::Display;
-use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::error;
+use std::fmt::{self, Display, Formatter};
+use std::io::Error as IoError;
 use std::string::String;
 
-pub trait Error = Display;
+use crate::error_codes;
+use crate::records::Provenance;
 
-pub fn internal_error<'a>(message: String) -> Box<dyn Error> {
-    return Box::new(IoError::new(
-        IoErrorKind::Other,
-        format!("Internal error ({})", message),
-    ));
+// What was being read when an I/O error occurred
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input {
+    Users,
+    Groups,
+    // OBJECTs given through --stdin
+    Objects,
 }
 
-pub fn usage_error<'a>(message: String) -> Box<dyn Error> {
-    return Box::new(IoError::new(IoErrorKind::Other, format!("{}", message)));
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKind {
+    User,
+    Group,
 }
 
-pub fn missing_field_error<'a>(field_name: &'static str) -> Box<dyn Error> {
-    return Box::new(IoError::new(
-        IoErrorKind::Other,
-        format!("Invalid line (missing field: {})", field_name),
-    ));
+#[derive(Debug)]
+pub enum Error {
+    // The command line could not be understood
+    Usage(String),
+    // A file or other source could not be read
+    Io {
+        input: Input,
+        path: String,
+        error: IoError,
+    },
+    // Output could not be written to a file
+    Write {
+        path: String,
+        error: IoError,
+    },
+    // A line (or record) of a source file could not be parsed. The line is None for
+    // sources that are not line-oriented.
+    Parse {
+        file: String,
+        line: Option<usize>,
+        reason: String,
+    },
+    // No user or group matched an OBJECT
+    NotFound {
+        kind: ObjectKind,
+        object: String,
+    },
+    // Something that should not happen, happened
+    Internal(String),
 }
 
-pub fn invalid_system_state<'a>(field_name: &'static str) -> Box<dyn Error> {
-    return Box::new(IoError::new(
-        IoErrorKind::Other,
-        format!(
+impl Error {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Usage(_) => error_codes::INVALID_USAGE,
+            Error::Io { input, .. } => match input {
+                Input::Users => error_codes::READ_USERS_ERROR,
+                Input::Groups => error_codes::READ_GROUPS_ERROR,
+                Input::Objects => error_codes::READ_STDIN_ERROR,
+            },
+            Error::Write { .. } => error_codes::WRITE_ERROR,
+            Error::Parse { .. } => error_codes::UNPARSEABLE_LINES,
+            Error::NotFound { kind, .. } => match kind {
+                ObjectKind::User => error_codes::USER_NOT_FOUND,
+                ObjectKind::Group => error_codes::GROUP_NOT_FOUND,
+            },
+            Error::Internal(_) => error_codes::INTERNAL_ERROR,
+        }
+    }
+
+    // A machine-readable name for the kind of error
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Usage(_) => "usage",
+            Error::Io { .. } | Error::Write { .. } => "io",
+            Error::Parse { .. } => "parse",
+            Error::NotFound { .. } => "notFound",
+            Error::Internal(_) => "internal",
+        }
+    }
+
+    // The error without its location, for errors whose location is reported separately
+    pub fn reason(&self) -> String {
+        match self {
+            Error::Parse { reason, .. } => reason.clone(),
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Usage(message) => write!(f, "{}", message),
+            Error::Io { input, path, error } => {
+                let input_name = match input {
+                    Input::Users => "users",
+                    Input::Groups => "groups",
+                    Input::Objects => "objects",
+                };
+                write!(f, "Could not read {} from {}: {}", input_name, path, error)
+            }
+            Error::Write { path, error } => write!(f, "Could not write to {}: {}", path, error),
+            Error::Parse {
+                file,
+                line: Some(line),
+                reason,
+            } => write!(f, "{}:{}: {}", file, line, reason),
+            Error::Parse { file, reason, .. } => write!(f, "{}: {}", file, reason),
+            Error::NotFound { kind, object } => match kind {
+                ObjectKind::User => write!(f, "Could not find user: {}", object),
+                ObjectKind::Group => write!(f, "Could not find group: {}", object),
+            },
+            Error::Internal(message) => write!(f, "Internal error ({})", message),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io { error, .. } | Error::Write { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+pub fn internal_error(message: String) -> Error {
+    return Error::Internal(message);
+}
+
+pub fn usage_error(message: String) -> Error {
+    return Error::Usage(message);
+}
+
+pub fn io_error(input: Input, path: String, error: IoError) -> Error {
+    return Error::Io {
+        input: input,
+        path: path,
+        error: error,
+    };
+}
+
+pub fn write_error(path: String, error: IoError) -> Error {
+    return Error::Write {
+        path: path,
+        error: error,
+    };
+}
+
+pub fn missing_field_error(field_name: &'static str, provenance: &Provenance) -> Error {
+    return Error::Parse {
+        file: provenance.file.clone(),
+        line: provenance.line,
+        reason: format!("Invalid line (missing field: {})", field_name),
+    };
+}
+
+pub fn invalid_system_state(field_name: &'static str, provenance: &Provenance) -> Error {
+    return Error::Parse {
+        file: provenance.file.clone(),
+        line: provenance.line,
+        reason: format!(
             "Invalid system configuration file state (invalid {})",
             field_name
         ),
-    ));
+    };
 }
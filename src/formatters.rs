@@ -0,0 +1,1020 @@
+// Contains the formatters that print a response in each output format. The CSV, TSV and
+// NDJSON formatters share a flattened view of each listing, with one record per row or line.
+
+use std::fs;
+use std::io::{self, Write};
+
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_json::{ser, Value};
+
+use crate::errors::{usage_error, write_error, Error};
+use crate::graph::{self, Graph};
+use crate::html;
+use crate::markdown;
+
+use crate::responses::{
+    print_response, BatchResult, OutputFormat, Provenance, TopLevelResponse, Warning,
+};
+use crate::sarif;
+
+pub trait ResponseFormatter {
+    // Prints `response` to stdout, along with any errors or warnings that belong on stderr.
+    // Fails if the output itself cannot be written.
+    fn print(&self, response: &TopLevelResponse) -> Result<(), Error>;
+}
+
+struct TextFormatter;
+
+struct JsonFormatter;
+
+struct SarifFormatter;
+
+// Comma- or tab-separated values, with a header row
+struct DelimitedFormatter {
+    separator: char,
+}
+
+// One JSON object per record, one record per line
+struct NdjsonFormatter;
+
+// A drawing of the users and groups, and the memberships between them
+struct GraphFormatter {
+    render: fn(&Graph) -> String,
+}
+
+pub fn formatter(format: &OutputFormat) -> Box<dyn ResponseFormatter> {
+    match format {
+        OutputFormat::Text => Box::new(TextFormatter),
+        OutputFormat::Json => Box::new(JsonFormatter),
+        OutputFormat::Sarif => Box::new(SarifFormatter),
+        OutputFormat::Csv => Box::new(DelimitedFormatter { separator: ',' }),
+        OutputFormat::Tsv => Box::new(DelimitedFormatter { separator: '\t' }),
+        OutputFormat::Ndjson => Box::new(NdjsonFormatter),
+        OutputFormat::Dot => Box::new(GraphFormatter { render: graph::dot }),
+        OutputFormat::Mermaid => Box::new(GraphFormatter {
+            render: graph::mermaid,
+        }),
+        OutputFormat::Markdown => Box::new(MarkdownFormatter),
+        OutputFormat::Template(template) => Box::new(TemplateFormatter {
+            template: template.clone(),
+        }),
+        OutputFormat::Long(listing) => Box::new(LongFormatter {
+            listing: listing.clone(),
+        }),
+        OutputFormat::Html(path) => Box::new(HtmlFormatter { path: path.clone() }),
+    }
+}
+
+fn print_warnings(warnings: &[Warning]) {
+    for warning in warnings {
+        eprintln!("Warning: {}", warning);
+    }
+}
+
+// Prints the error of a response that failed, or of each failed entry of a batch, on stderr,
+// for the formatters that leave errors out of their output. A failed entry does not stop the
+// batch, so it is only an `Error:`.
+fn report_errors(response: &TopLevelResponse) {
+    match response {
+        TopLevelResponse::NoResponse(result) => eprintln!("Fatal: {}", result.error),
+        TopLevelResponse::Batch(result) => {
+            for entry in result.entries.iter() {
+                if let TopLevelResponse::NoResponse(result) = &entry.response {
+                    eprintln!("Error: {}", result.error);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+impl ResponseFormatter for TextFormatter {
+    fn print(&self, response: &TopLevelResponse) -> Result<(), Error> {
+        print_warnings(response.warnings());
+        print_response(response, false);
+        return Ok(());
+    }
+}
+
+impl ResponseFormatter for JsonFormatter {
+    fn print(&self, response: &TopLevelResponse) -> Result<(), Error> {
+        print_response(response, true);
+        return Ok(());
+    }
+}
+
+impl ResponseFormatter for SarifFormatter {
+    fn print(&self, response: &TopLevelResponse) -> Result<(), Error> {
+        let log = sarif::sarif_log(response);
+        let json = ser::to_string(&log).expect("Could not stringify JSON");
+        println!("{}", json);
+        return Ok(());
+    }
+}
+
+// A listing flattened into rows of plain values (strings, numbers, booleans or null)
+pub struct Table {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl Table {
+    fn new(columns: Vec<&str>) -> Table {
+        return Table {
+            columns: columns.into_iter().map(str::to_string).collect(),
+            rows: vec![],
+        };
+    }
+}
+
+fn value_of<T: Serialize>(value: T) -> Value {
+    return serde_json::to_value(value).expect("Could not convert to JSON");
+}
+
+// The locations that a membership comes from, e.g. `/etc/passwd:3, /etc/group:12`
+fn provenance_value(provenance: &Option<Provenance>) -> Value {
+    match provenance {
+        Some(provenance) => {
+            let locations: Vec<String> = provenance
+                .user
+                .iter()
+                .chain(provenance.group.iter())
+                .map(|location| location.to_string())
+                .collect();
+            Value::String(locations.join(", "))
+        }
+        None => Value::Null,
+    }
+}
+
+// Adds a `provenance` column if any row has one, i.e. with --explain
+fn with_provenance(mut table: Table, provenances: Vec<&Option<Provenance>>) -> Table {
+    if provenances.iter().any(|provenance| provenance.is_some()) {
+        table.columns.push("provenance".to_string());
+        for (row, provenance) in table.rows.iter_mut().zip(provenances) {
+            row.push(provenance_value(provenance));
+        }
+    }
+    return table;
+}
+
+// None for responses that are not listings, i.e. errors and batches
+pub fn table(response: &TopLevelResponse) -> Option<Table> {
+    let table = match response {
+        TopLevelResponse::GroupOverview(result) => {
+            let mut table = Table::new(vec!["name", "id"]);
+            for group in result.groups.iter() {
+                table
+                    .rows
+                    .push(vec![value_of(&group.name), value_of(group.id)]);
+            }
+            table
+        }
+        TopLevelResponse::UserOverview(result) => {
+            let mut table = Table::new(vec![
+                "name",
+                "id",
+                "primary_group_id",
+                "primary_group",
+                "supplementary_group_count",
+                "shell",
+            ]);
+            for user in result.users.iter() {
+                table.rows.push(vec![
+                    value_of(&user.name),
+                    value_of(user.id),
+                    value_of(user.primary_group_id),
+                    value_of(&user.primary_group),
+                    value_of(user.supplementary_group_count),
+                    value_of(&user.shell),
+                ]);
+            }
+            table
+        }
+        // One row per membership, or a row without a user for a group without users
+        TopLevelResponse::GroupTree(result) => {
+            let mut table = Table::new(vec![
+                "group",
+                "group_id",
+                "user",
+                "user_id",
+                "role",
+                "membership",
+            ]);
+            let mut provenances = vec![];
+            for group in result.groups.iter() {
+                if group.users.is_empty() {
+                    table.rows.push(vec![
+                        value_of(&group.name),
+                        value_of(group.id),
+                        Value::Null,
+                        Value::Null,
+                        Value::Null,
+                        Value::Null,
+                    ]);
+                    provenances.push(&None);
+                }
+                for user in group.users.iter() {
+                    table.rows.push(vec![
+                        value_of(&group.name),
+                        value_of(group.id),
+                        value_of(&user.name),
+                        value_of(user.id),
+                        value_of(user.role),
+                        value_of(user.membership),
+                    ]);
+                    provenances.push(&user.provenance);
+                }
+            }
+            with_provenance(table, provenances)
+        }
+        // One row per membership, or a row without a group for a user without groups
+        TopLevelResponse::UserTree(result) => {
+            let mut table = Table::new(vec!["user", "user_id", "group", "group_id", "membership"]);
+            let mut provenances = vec![];
+            for user in result.users.iter() {
+                if user.groups.is_empty() {
+                    table.rows.push(vec![
+                        value_of(&user.name),
+                        value_of(user.id),
+                        Value::Null,
+                        Value::Null,
+                        Value::Null,
+                    ]);
+                    provenances.push(&None);
+                }
+                for group in user.groups.iter() {
+                    table.rows.push(vec![
+                        value_of(&user.name),
+                        value_of(user.id),
+                        value_of(&group.name),
+                        value_of(group.id),
+                        value_of(group.membership),
+                    ]);
+                    provenances.push(&group.provenance);
+                }
+            }
+            with_provenance(table, provenances)
+        }
+        TopLevelResponse::UserQuery(result) => {
+            let mut table = Table::new(vec!["name", "id", "membership"]);
+            for group in result.user.groups.iter() {
+                table.rows.push(vec![
+                    value_of(&group.name),
+                    value_of(group.id),
+                    value_of(group.membership),
+                ]);
+            }
+            let provenances = result.user.groups.iter().map(|g| &g.provenance).collect();
+            with_provenance(table, provenances)
+        }
+        TopLevelResponse::GroupQuery(result) => {
+            let mut table = Table::new(vec![
+                "name",
+                "id",
+                "role",
+                "membership",
+                "full_name",
+                "home_directory",
+                "shell",
+            ]);
+            for user in result.group.users.iter() {
+                table.rows.push(vec![
+                    value_of(&user.name),
+                    value_of(user.id),
+                    value_of(user.role),
+                    value_of(user.membership),
+                    value_of(&user.full_name),
+                    value_of(&user.home_directory),
+                    value_of(&user.shell),
+                ]);
+            }
+            let provenances = result.group.users.iter().map(|u| &u.provenance).collect();
+            with_provenance(table, provenances)
+        }
+        TopLevelResponse::MembershipQuery(result) => {
+            let mut table = Table::new(vec!["user_name", "group_name", "is_member"]);
+            table.rows.push(vec![
+                value_of(&result.user_name),
+                value_of(&result.group_name),
+                value_of(result.is_member),
+            ]);
+            with_provenance(table, vec![&result.provenance])
+        }
+        TopLevelResponse::Audit(result) => {
+            let mut table = Table::new(vec!["id", "severity", "message", "locations"]);
+            for finding in result.findings.iter() {
+                let locations: Vec<String> = finding
+                    .locations
+                    .iter()
+                    .map(|location| location.to_string())
+                    .collect();
+                table.rows.push(vec![
+                    value_of(&finding.id),
+                    value_of(finding.severity),
+                    value_of(&finding.message),
+                    value_of(locations.join(" ")),
+                ]);
+            }
+            table
+        }
+        TopLevelResponse::Compliance(result) => {
+            let mut table = Table::new(vec!["id", "status", "title", "failures", "remediation"]);
+            for check in result.checks.iter() {
+                table.rows.push(vec![
+                    value_of(&check.id),
+                    value_of(check.status),
+                    value_of(&check.title),
+                    value_of(check.findings.len()),
+                    value_of(&check.remediation),
+                ]);
+            }
+            table
+        }
+        // A row per user and a column per group, with empty cells for non-members. The group
+        // columns are keyed `group:NAME:GID`, as group names can be shared (or be `user`).
+        TopLevelResponse::Matrix(result) => {
+            let mut table = Table::new(vec!["user"]);
+            table.columns.extend(
+                result
+                    .groups
+                    .iter()
+                    .map(|group| format!("group:{}:{}", group.name, group.id)),
+            );
+            for (user, cells) in result.users.iter().zip(result.grid()) {
+                let mut row = vec![value_of(&user.name)];
+                row.extend(cells.into_iter().map(value_of));
+                table.rows.push(row);
+            }
+            table
+        }
+        TopLevelResponse::Report(_)
+        | TopLevelResponse::Batch(_)
+        | TopLevelResponse::NoResponse(_) => return None,
+    };
+    return Some(table);
+}
+
+// The tables of the entries of a batch, each row led by the OBJECT it was listed for.
+// Entries that failed are left out, to be reported by the caller.
+fn batch_table(result: &BatchResult) -> Option<Table> {
+    let mut batch_table: Option<Table> = None;
+    for entry in result.entries.iter() {
+        let table = match table(&entry.response) {
+            Some(table) => table,
+            None => continue,
+        };
+        let batch_table = batch_table.get_or_insert_with(|| {
+            let mut batch_table = Table::new(vec!["object"]);
+            batch_table.columns.extend(table.columns.iter().cloned());
+            batch_table
+        });
+        for row in table.rows {
+            let mut batch_row = vec![value_of(&entry.object)];
+            batch_row.extend(row);
+            batch_table.rows.push(batch_row);
+        }
+    }
+    return batch_table;
+}
+
+// Strings are printed bare, and null as an empty field
+fn field(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+impl DelimitedFormatter {
+    // CSV fields are quoted as in RFC 4180. TSV fields cannot be quoted, so tabs, newlines
+    // and backslashes are escaped with a backslash instead.
+    fn escape(&self, field: String) -> String {
+        if self.separator == '\t' {
+            return field
+                .replace('\\', "\\\\")
+                .replace('\t', "\\t")
+                .replace('\n', "\\n")
+                .replace('\r', "\\r");
+        }
+        if field.contains([self.separator, '"', '\n', '\r']) {
+            return format!("\"{}\"", field.replace('"', "\"\""));
+        }
+        return field;
+    }
+
+    fn print_row(&self, fields: Vec<String>) {
+        let escaped: Vec<String> = fields.into_iter().map(|f| self.escape(f)).collect();
+        println!("{}", escaped.join(&self.separator.to_string()));
+    }
+}
+
+impl ResponseFormatter for DelimitedFormatter {
+    fn print(&self, response: &TopLevelResponse) -> Result<(), Error> {
+        print_warnings(response.warnings());
+        report_errors(response);
+        let table = match response {
+            TopLevelResponse::Batch(result) => batch_table(result),
+            other => table(other),
+        };
+        if let Some(table) = table {
+            self.print_row(table.columns.clone());
+            for row in table.rows.iter() {
+                self.print_row(row.iter().map(field).collect());
+            }
+        }
+        return Ok(());
+    }
+}
+
+// A row of a table, serialized as an object with the table's columns as keys, in order
+struct Record<'a> {
+    columns: &'a [String],
+    values: &'a [Value],
+}
+
+impl<'a> Serialize for Record<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.columns.len()))?;
+        for (column, value) in self.columns.iter().zip(self.values.iter()) {
+            map.serialize_entry(column, value)?;
+        }
+        return map.end();
+    }
+}
+
+// Errors are printed as JSON objects in place of records, as in JSON output
+impl ResponseFormatter for NdjsonFormatter {
+    fn print(&self, response: &TopLevelResponse) -> Result<(), Error> {
+        print_warnings(response.warnings());
+        let table = match response {
+            TopLevelResponse::Batch(result) => {
+                for entry in result.entries.iter() {
+                    if let TopLevelResponse::NoResponse(_) = &entry.response {
+                        print_response(&entry.response, true);
+                    }
+                }
+                batch_table(result)
+            }
+            TopLevelResponse::NoResponse(_) => {
+                print_response(response, true);
+                None
+            }
+            other => table(other),
+        };
+        if let Some(table) = table {
+            for row in table.rows.iter() {
+                let record = Record {
+                    columns: &table.columns,
+                    values: row,
+                };
+                let json = ser::to_string(&record).expect("Could not stringify JSON");
+                println!("{}", json);
+            }
+        }
+        return Ok(());
+    }
+}
+
+// A piece of a --template: literal text, or the name of a field to substitute
+#[derive(Clone, PartialEq, Eq)]
+enum TemplatePart {
+    Text(String),
+    Field(String),
+}
+
+// A --template such as `{name}:{id}`, printed once per record. `{{` and `}}` stand for
+// literal braces, and `\t`, `\n`, `\0` and `\\` for a tab, newline, NUL and backslash.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<TemplatePart>,
+}
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Template, Error> {
+        let mut parts = vec![];
+        let mut text = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => text.push(match chars.next() {
+                    Some('t') => '\t',
+                    Some('n') => '\n',
+                    Some('0') => '\0',
+                    Some('\\') => '\\',
+                    Some(other) => {
+                        return Err(usage_error(format!(
+                            "Unknown escape `\\{}` in template",
+                            other
+                        )));
+                    }
+                    None => {
+                        return Err(usage_error("Template ends with a lone `\\`".to_string()));
+                    }
+                }),
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = match rest.find('}') {
+                        Some(end) => end,
+                        None => {
+                            return Err(usage_error(
+                                "Unclosed `{` in template (use `{{` for a literal brace)"
+                                    .to_string(),
+                            ));
+                        }
+                    };
+                    let name = rest[..end].trim().to_string();
+                    if name.is_empty() {
+                        return Err(usage_error("Empty field name in template".to_string()));
+                    }
+                    if !text.is_empty() {
+                        parts.push(TemplatePart::Text(text.clone()));
+                        text.clear();
+                    }
+                    parts.push(TemplatePart::Field(name));
+                    chars = rest[end + 1..].chars();
+                }
+                '}' => {
+                    return Err(usage_error(
+                        "Unmatched `}` in template (use `}}` for a literal brace)".to_string(),
+                    ));
+                }
+                other => text.push(other),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(TemplatePart::Text(text));
+        }
+        return Ok(Template { parts: parts });
+    }
+
+    fn fields(&self) -> impl Iterator<Item = &String> {
+        return self.parts.iter().filter_map(|part| match part {
+            TemplatePart::Field(name) => Some(name),
+            TemplatePart::Text(_) => None,
+        });
+    }
+
+    // Fails on the first field that the records of `response` do not have
+    pub fn check(&self, response: &TopLevelResponse) -> Result<(), Error> {
+        let table = match response {
+            TopLevelResponse::Batch(result) => batch_table(result),
+            other => table(other),
+        };
+        let table = match table {
+            Some(table) => table,
+            None => return Ok(()),
+        };
+        for name in self.fields() {
+            if !table.columns.contains(name) {
+                return Err(usage_error(format!(
+                    "Unknown template field `{}` (expected one of: {})",
+                    name,
+                    table.columns.join(", ")
+                )));
+            }
+        }
+        return Ok(());
+    }
+
+    // Records end with a newline, unless the template ends them with a NUL itself
+    fn render(&self, columns: &[String], values: &[Value]) -> String {
+        let mut rendered = String::new();
+        for part in self.parts.iter() {
+            match part {
+                TemplatePart::Text(text) => rendered.push_str(text),
+                TemplatePart::Field(name) => {
+                    if let Some(i) = columns.iter().position(|column| column == name) {
+                        rendered.push_str(&field(&values[i]));
+                    }
+                }
+            }
+        }
+        if !rendered.ends_with('\0') {
+            rendered.push('\n');
+        }
+        return rendered;
+    }
+}
+
+// Failed entries of a batch are reported on stderr, and left out of the graph
+impl ResponseFormatter for GraphFormatter {
+    fn print(&self, response: &TopLevelResponse) -> Result<(), Error> {
+        print_warnings(response.warnings());
+        report_errors(response);
+        if let Some(graph) = Graph::of(response) {
+            println!("{}", (self.render)(&graph));
+        }
+        return Ok(());
+    }
+}
+
+struct MarkdownFormatter;
+
+// Failed entries of a batch are reported on stderr, and left out of the document
+impl ResponseFormatter for MarkdownFormatter {
+    fn print(&self, response: &TopLevelResponse) -> Result<(), Error> {
+        print_warnings(response.warnings());
+        report_errors(response);
+        if let Some(markdown) = markdown::markdown(response) {
+            println!("{}", markdown);
+        }
+        return Ok(());
+    }
+}
+
+struct TemplateFormatter {
+    template: Template,
+}
+
+impl ResponseFormatter for TemplateFormatter {
+    fn print(&self, response: &TopLevelResponse) -> Result<(), Error> {
+        print_warnings(response.warnings());
+        report_errors(response);
+        let table = match response {
+            TopLevelResponse::Batch(result) => batch_table(result),
+            other => table(other),
+        };
+        if let Some(table) = table {
+            let mut stdout = io::stdout().lock();
+            for row in table.rows.iter() {
+                let rendered = self.template.render(&table.columns, row);
+                stdout
+                    .write_all(rendered.as_bytes())
+                    .map_err(|e| write_error("stdout".to_string(), e))?;
+            }
+        }
+        return Ok(());
+    }
+}
+
+// What -l sorts by. Users are sorted by their number of supplementary groups for `members`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Name,
+    Id,
+    Members,
+}
+
+impl SortKey {
+    pub fn from_name(name: &str) -> Option<SortKey> {
+        match name {
+            "name" => Some(SortKey::Name),
+            "id" => Some(SortKey::Id),
+            "members" => Some(SortKey::Members),
+            _ => None,
+        }
+    }
+}
+
+// The options of the -l listing
+#[derive(Clone, PartialEq, Eq)]
+pub struct LongListing {
+    // None for the default columns of the listing
+    pub columns: Option<Vec<String>>,
+    // None to keep the order of the source files
+    pub sort: Option<SortKey>,
+    pub reverse: bool,
+}
+
+const GROUP_COLUMNS: [&'static str; 4] = ["name", "id", "count", "members"];
+const USER_COLUMNS: [&'static str; 6] = ["name", "uid", "gid", "group", "groups", "shell"];
+const DEFAULT_USER_COLUMNS: [&'static str; 4] = ["name", "uid", "group", "shell"];
+
+// Numeric columns are aligned to the right
+const NUMERIC_COLUMNS: [&'static str; 5] = ["id", "count", "uid", "gid", "groups"];
+
+// A row of a -l listing, along with the values it can be sorted by
+struct LongRow {
+    name: String,
+    id: i64,
+    members: usize,
+    cells: Vec<(&'static str, String)>,
+}
+
+impl LongRow {
+    fn cell(&self, column: &str) -> &str {
+        return self
+            .cells
+            .iter()
+            .find(|(name, _)| *name == column)
+            .map(|(_, value)| value.as_str())
+            .unwrap_or("");
+    }
+}
+
+fn long_rows(response: &TopLevelResponse) -> Option<(Vec<LongRow>, &'static [&'static str])> {
+    match response {
+        TopLevelResponse::GroupOverview(result) => {
+            let rows = result
+                .groups
+                .iter()
+                .map(|group| {
+                    let members = group.members.clone().unwrap_or_default();
+                    LongRow {
+                        name: group.name.clone(),
+                        id: group.id,
+                        members: members.len(),
+                        cells: vec![
+                            ("name", group.name.clone()),
+                            ("id", group.id.to_string()),
+                            ("count", members.len().to_string()),
+                            ("members", members.join(", ")),
+                        ],
+                    }
+                })
+                .collect();
+            Some((rows, &GROUP_COLUMNS))
+        }
+        TopLevelResponse::UserOverview(result) => {
+            let rows = result
+                .users
+                .iter()
+                .map(|user| LongRow {
+                    name: user.name.clone(),
+                    id: user.id,
+                    members: user.supplementary_group_count,
+                    cells: vec![
+                        ("name", user.name.clone()),
+                        ("uid", user.id.to_string()),
+                        ("gid", user.primary_group_id.to_string()),
+                        (
+                            "group",
+                            match &user.primary_group {
+                                Some(group) => group.clone(),
+                                None => user.primary_group_id.to_string(),
+                            },
+                        ),
+                        ("groups", user.supplementary_group_count.to_string()),
+                        ("shell", user.shell.clone()),
+                    ],
+                })
+                .collect();
+            Some((rows, &USER_COLUMNS))
+        }
+        _ => None,
+    }
+}
+
+// The width of the terminal that stdout is, if it is one
+fn terminal_width() -> Option<usize> {
+    if unsafe { libc::isatty(libc::STDOUT_FILENO) } != 1 {
+        return None;
+    }
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    if unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0
+        && size.ws_col > 0
+    {
+        return Some(size.ws_col as usize);
+    }
+    return std::env::var("COLUMNS").ok().and_then(|c| c.parse().ok());
+}
+
+// Cuts `line` down to `width` characters, marking the cut with an ellipsis
+fn truncate(line: String, width: usize) -> String {
+    if line.chars().count() <= width || width == 0 {
+        return line;
+    }
+    let mut truncated: String = line.chars().take(width - 1).collect();
+    truncated.push('…');
+    return truncated;
+}
+
+impl LongListing {
+    fn columns(&self, default: &[&'static str]) -> Vec<String> {
+        match &self.columns {
+            Some(columns) => columns.clone(),
+            None => default.iter().map(|column| column.to_string()).collect(),
+        }
+    }
+
+    // Fails on the first column that the listing does not have
+    pub fn check(&self, response: &TopLevelResponse) -> Result<(), Error> {
+        let available = match long_rows(response) {
+            Some((_, available)) => available,
+            None => return Ok(()),
+        };
+        for column in self.columns(available) {
+            if !available.contains(&column.as_str()) {
+                return Err(usage_error(format!(
+                    "Unknown column `{}` (expected one of: {})",
+                    column,
+                    available.join(", ")
+                )));
+            }
+        }
+        return Ok(());
+    }
+}
+
+struct LongFormatter {
+    listing: LongListing,
+}
+
+// Lines are cut down to the width of the terminal, if stdout is one
+impl ResponseFormatter for LongFormatter {
+    fn print(&self, response: &TopLevelResponse) -> Result<(), Error> {
+        let (mut rows, available) = match long_rows(response) {
+            Some(rows) => rows,
+            None => return TextFormatter.print(response),
+        };
+        print_warnings(response.warnings());
+
+        match self.listing.sort {
+            Some(SortKey::Name) => rows.sort_by(|a, b| a.name.cmp(&b.name)),
+            Some(SortKey::Id) => rows.sort_by_key(|row| row.id),
+            Some(SortKey::Members) => rows.sort_by_key(|row| row.members),
+            None => {}
+        }
+        if self.listing.reverse {
+            rows.reverse();
+        }
+
+        let default_columns = match response {
+            TopLevelResponse::UserOverview(_) => &DEFAULT_USER_COLUMNS[..],
+            _ => available,
+        };
+        let columns = self.listing.columns(default_columns);
+        let header: Vec<String> = columns.iter().map(|c| c.to_uppercase()).collect();
+        let mut lines: Vec<Vec<String>> = vec![header];
+        for row in rows.iter() {
+            lines.push(columns.iter().map(|c| row.cell(c).to_string()).collect());
+        }
+
+        let mut widths = vec![0; columns.len()];
+        for line in lines.iter() {
+            for (i, cell) in line.iter().enumerate() {
+                widths[i] = widths[i].max(cell.chars().count());
+            }
+        }
+
+        let width = terminal_width();
+        for line in lines {
+            let mut text = String::new();
+            for (i, cell) in line.iter().enumerate() {
+                if i != 0 {
+                    text.push_str("  ");
+                }
+                let is_last = i == line.len() - 1;
+                if NUMERIC_COLUMNS.contains(&columns[i].as_str()) {
+                    text.push_str(&format!("{:>w$}", cell, w = widths[i]));
+                } else if is_last {
+                    text.push_str(cell);
+                } else {
+                    text.push_str(&format!("{:<w$}", cell, w = widths[i]));
+                }
+            }
+            // An empty last cell would otherwise leave the separator trailing
+            let text = text.trim_end().to_string();
+            match width {
+                Some(width) => println!("{}", truncate(text, width)),
+                None => println!("{}", text),
+            }
+        }
+        return Ok(());
+    }
+}
+
+// Writes the HTML report to a file, or to stdout if the path is `-`
+struct HtmlFormatter {
+    path: String,
+}
+
+impl ResponseFormatter for HtmlFormatter {
+    fn print(&self, response: &TopLevelResponse) -> Result<(), Error> {
+        print_warnings(response.warnings());
+        let report = match response {
+            TopLevelResponse::Report(result) => html::report(result),
+            other => return TextFormatter.print(other),
+        };
+        if self.path == "-" {
+            println!("{}", report);
+        } else {
+            fs::write(&self.path, report + "\n").map_err(|e| write_error(self.path.clone(), e))?;
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::responses::{MatrixAxis, MatrixQueryResult};
+
+    fn text(text: &str) -> TemplatePart {
+        return TemplatePart::Text(text.to_string());
+    }
+
+    fn field(name: &str) -> TemplatePart {
+        return TemplatePart::Field(name.to_string());
+    }
+
+    fn parts(template: &str) -> Vec<TemplatePart> {
+        match Template::parse(template) {
+            Ok(template) => template.parts,
+            Err(error) => panic!("rejected `{}`: {}", template, error),
+        }
+    }
+
+    fn axis(name: &str, id: i64) -> MatrixAxis {
+        return MatrixAxis {
+            name: name.to_string(),
+            id: id,
+        };
+    }
+
+    #[test]
+    fn keys_matrix_columns_uniquely() {
+        let response = TopLevelResponse::Matrix(MatrixQueryResult {
+            api_version: String::new(),
+            source_files: vec![],
+            warnings: vec![],
+            users: vec![axis("alice", 1000)],
+            groups: vec![axis("user", 100), axis("wheel", 10), axis("wheel", 11)],
+            memberships: vec![],
+        });
+        let columns = match table(&response) {
+            Some(table) => table.columns,
+            None => panic!("no table for the matrix"),
+        };
+        assert!(columns == vec!["user", "group:user:100", "group:wheel:10", "group:wheel:11"]);
+    }
+
+    #[test]
+    fn parses_fields_and_text() {
+        assert!(parts("{name}:{id}") == vec![field("name"), text(":"), field("id")]);
+        assert!(parts("user { name }!") == vec![text("user "), field("name"), text("!")]);
+        assert!(parts("").is_empty());
+    }
+
+    #[test]
+    fn parses_doubled_braces_as_literal_braces() {
+        assert!(parts("{{{name}}}") == vec![text("{"), field("name"), text("}")]);
+        assert!(parts("{{}}") == vec![text("{}")]);
+    }
+
+    #[test]
+    fn parses_escapes() {
+        assert!(
+            parts("{name}\\t{id}\\n") == vec![field("name"), text("\t"), field("id"), text("\n")]
+        );
+        assert!(parts("\\\\{name}\\0") == vec![text("\\"), field("name"), text("\0")]);
+    }
+
+    #[test]
+    fn rejects_malformed_templates() {
+        for template in ["{name", "name}", "{}", "{ }", "\\x", "{name}\\"].iter() {
+            assert!(
+                Template::parse(template).is_err(),
+                "accepted `{}`",
+                template
+            );
+        }
+    }
+
+    #[test]
+    fn renders_records_with_a_newline_unless_ended_by_nul() {
+        let columns = vec!["name".to_string(), "id".to_string()];
+        let values = vec![Value::from("wheel"), Value::from(10)];
+        let template = Template {
+            parts: parts("{name}:{id}"),
+        };
+        assert_eq!(template.render(&columns, &values), "wheel:10\n");
+        let template = Template {
+            parts: parts("{name}\\0"),
+        };
+        assert_eq!(template.render(&columns, &values), "wheel\0");
+    }
+
+    #[test]
+    fn quotes_csv_fields_as_in_rfc_4180() {
+        let csv = DelimitedFormatter { separator: ',' };
+        assert_eq!(csv.escape("wheel".to_string()), "wheel");
+        assert_eq!(csv.escape("Smith, Alice".to_string()), "\"Smith, Alice\"");
+        assert_eq!(csv.escape("say \"hi\"".to_string()), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv.escape("two\nlines".to_string()), "\"two\nlines\"");
+        assert_eq!(csv.escape("a\tb\\c".to_string()), "a\tb\\c");
+    }
+
+    #[test]
+    fn escapes_tsv_fields_with_backslashes() {
+        let tsv = DelimitedFormatter { separator: '\t' };
+        assert_eq!(tsv.escape("wheel".to_string()), "wheel");
+        assert_eq!(tsv.escape("a\tb".to_string()), "a\\tb");
+        assert_eq!(tsv.escape("two\r\nlines".to_string()), "two\\r\\nlines");
+        assert_eq!(tsv.escape("C:\\home".to_string()), "C:\\\\home");
+        assert_eq!(
+            tsv.escape("Smith, \"Alice\"".to_string()),
+            "Smith, \"Alice\""
+        );
+    }
+}
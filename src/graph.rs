@@ -0,0 +1,206 @@
+// Contains the Graphviz DOT and Mermaid renderings of the bipartite graph of users and
+// groups, with an edge for each membership
+
+use crate::responses::{Membership, TopLevelResponse};
+
+// A user or group, told apart from others of the same name by its ID
+#[derive(PartialEq, Eq)]
+pub struct Node {
+    pub name: String,
+    pub id: i64,
+}
+
+// Users and groups are referred to by their position in the graph
+pub struct Edge {
+    pub user: usize,
+    pub group: usize,
+    pub membership: Membership,
+}
+
+// Users and groups are kept in order of first appearance
+pub struct Graph {
+    pub users: Vec<Node>,
+    pub groups: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+// The position of the node called `name` with ID `id`, added if it is not there yet
+fn add_node(nodes: &mut Vec<Node>, name: &str, id: i64) -> usize {
+    if let Some(i) = nodes.iter().position(|n| n.name == name && n.id == id) {
+        return i;
+    }
+    nodes.push(Node {
+        name: name.to_string(),
+        id: id,
+    });
+    return nodes.len() - 1;
+}
+
+impl Graph {
+    fn add_user(&mut self, name: &str, id: i64) -> usize {
+        return add_node(&mut self.users, name, id);
+    }
+
+    fn add_group(&mut self, name: &str, id: i64) -> usize {
+        return add_node(&mut self.groups, name, id);
+    }
+
+    fn add_edge(&mut self, user: usize, group: usize, membership: Membership) {
+        if !self
+            .edges
+            .iter()
+            .any(|e| e.user == user && e.group == group)
+        {
+            self.edges.push(Edge {
+                user: user,
+                group: group,
+                membership: membership,
+            });
+        }
+    }
+
+    fn add_response(&mut self, response: &TopLevelResponse) {
+        match response {
+            TopLevelResponse::GroupTree(result) => {
+                for group in result.groups.iter() {
+                    let g = self.add_group(&group.name, group.id);
+                    for user in group.users.iter() {
+                        let u = self.add_user(&user.name, user.id);
+                        self.add_edge(u, g, user.membership);
+                    }
+                }
+            }
+            TopLevelResponse::UserTree(result) => {
+                for user in result.users.iter() {
+                    let u = self.add_user(&user.name, user.id);
+                    for group in user.groups.iter() {
+                        let g = self.add_group(&group.name, group.id);
+                        let membership = group.membership.unwrap_or(Membership::Supplementary);
+                        self.add_edge(u, g, membership);
+                    }
+                }
+            }
+            TopLevelResponse::UserQuery(result) => {
+                let u = self.add_user(&result.user.user_name, result.user.user_id);
+                for group in result.user.groups.iter() {
+                    let g = self.add_group(&group.name, group.id);
+                    let membership = group.membership.unwrap_or(Membership::Supplementary);
+                    self.add_edge(u, g, membership);
+                }
+            }
+            TopLevelResponse::GroupQuery(result) => {
+                let g = self.add_group(&result.group.group_name, result.group.group_id);
+                for user in result.group.users.iter() {
+                    let u = self.add_user(&user.name, user.id);
+                    self.add_edge(u, g, user.membership);
+                }
+            }
+            TopLevelResponse::Batch(result) => {
+                for entry in result.entries.iter() {
+                    self.add_response(&entry.response);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // The whole graph for the tree views, or the subgraph around each user or group
+    // for -u and -g. None for responses without memberships to draw. Only the groups (or
+    // users) that a tree is made of are drawn without any memberships.
+    pub fn of(response: &TopLevelResponse) -> Option<Graph> {
+        match response {
+            TopLevelResponse::GroupTree(_)
+            | TopLevelResponse::UserTree(_)
+            | TopLevelResponse::UserQuery(_)
+            | TopLevelResponse::GroupQuery(_)
+            | TopLevelResponse::Batch(_) => {}
+            _ => return None,
+        }
+        let mut graph = Graph {
+            users: vec![],
+            groups: vec![],
+            edges: vec![],
+        };
+        graph.add_response(response);
+        return Some(graph);
+    }
+}
+
+fn membership_name(membership: Membership) -> &'static str {
+    match membership {
+        Membership::Primary => "primary",
+        Membership::Supplementary => "supplementary",
+        Membership::Both => "both",
+    }
+}
+
+fn dot_string(text: &str) -> String {
+    return format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""));
+}
+
+// Users are ellipses and groups boxes, identified by name and ID, so that records sharing
+// a name are drawn apart. Edges to a primary group are bold, and edges to a group that
+// merely lists the user are dashed.
+pub fn dot(graph: &Graph) -> String {
+    let user_id = |user: &Node| dot_string(&format!("user:{}:{}", user.name, user.id));
+    let group_id = |group: &Node| dot_string(&format!("group:{}:{}", group.name, group.id));
+
+    let mut lines = vec!["graph groupls {".to_string(), "  rankdir=LR;".to_string()];
+    for user in graph.users.iter() {
+        lines.push(format!(
+            "  {} [label={}, tooltip={}, shape=ellipse];",
+            user_id(user),
+            dot_string(&user.name),
+            dot_string(&format!("UID {}", user.id))
+        ));
+    }
+    for group in graph.groups.iter() {
+        lines.push(format!(
+            "  {} [label={}, tooltip={}, shape=box];",
+            group_id(group),
+            dot_string(&group.name),
+            dot_string(&format!("GID {}", group.id))
+        ));
+    }
+    for edge in graph.edges.iter() {
+        let style = match edge.membership {
+            Membership::Supplementary => "dashed",
+            Membership::Primary | Membership::Both => "bold",
+        };
+        lines.push(format!(
+            "  {} -- {} [style={}, tooltip={}];",
+            user_id(&graph.users[edge.user]),
+            group_id(&graph.groups[edge.group]),
+            style,
+            dot_string(membership_name(edge.membership))
+        ));
+    }
+    lines.push("}".to_string());
+    return lines.join("\n");
+}
+
+// Mermaid labels cannot contain a bare double quote
+fn mermaid_string(text: &str) -> String {
+    return format!("\"{}\"", text.replace('"', "#quot;"));
+}
+
+// Users are rounded and groups square. Edges to a primary group are thick, and edges to
+// a group that merely lists the user are dotted. Nodes are numbered, as Mermaid IDs
+// cannot hold every character that a name can.
+pub fn mermaid(graph: &Graph) -> String {
+    let mut lines = vec!["flowchart LR".to_string()];
+    for (i, user) in graph.users.iter().enumerate() {
+        lines.push(format!("  u{}([{}])", i, mermaid_string(&user.name)));
+    }
+    for (i, group) in graph.groups.iter().enumerate() {
+        lines.push(format!("  g{}[{}]", i, mermaid_string(&group.name)));
+    }
+    for edge in graph.edges.iter() {
+        let link = match edge.membership {
+            Membership::Supplementary => "-.-",
+            Membership::Primary | Membership::Both => "===",
+        };
+        lines.push(format!("  u{} {} g{}", edge.user, link, edge.group));
+    }
+    return lines.join("\n");
+}
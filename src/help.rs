@@ -0,0 +1,305 @@
+// Contains the structured description of groupls printed by `--help --json`, for wrappers
+// that would otherwise have to parse the usage text
+
+use serde_derive::Serialize;
+
+use crate::error_codes;
+use crate::responses::OutputFormat;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HelpResult {
+    pub api_version: String,
+    pub options: Vec<OptionHelp>,
+    pub modes: Vec<ModeHelp>,
+    pub exit_codes: Vec<ExitCodeHelp>,
+}
+
+#[derive(Serialize)]
+pub struct OptionHelp {
+    pub name: String,
+    // Other spellings of the option, e.g. `-u` for `--user`
+    pub aliases: Vec<String>,
+    // A placeholder for the value the option takes, if it takes one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    // The values the option accepts, if there is a fixed set of them
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub choices: Vec<String>,
+    pub description: String,
+}
+
+#[derive(Serialize)]
+pub struct ModeHelp {
+    pub name: String,
+    pub usage: String,
+    pub description: String,
+}
+
+#[derive(Serialize)]
+pub struct ExitCodeHelp {
+    pub code: i32,
+    pub description: String,
+}
+
+fn option_help(
+    name: &str,
+    aliases: &[&str],
+    value: Option<&str>,
+    choices: &[&str],
+    description: &str,
+) -> OptionHelp {
+    return OptionHelp {
+        name: name.to_string(),
+        aliases: aliases.iter().map(|alias| alias.to_string()).collect(),
+        value: value.map(str::to_string),
+        choices: choices.iter().map(|choice| choice.to_string()).collect(),
+        description: description.to_string(),
+    };
+}
+
+fn flag_help(name: &str, aliases: &[&str], description: &str) -> OptionHelp {
+    return option_help(name, aliases, None, &[], description);
+}
+
+fn mode_help(name: &str, usage: &str, description: &str) -> ModeHelp {
+    return ModeHelp {
+        name: name.to_string(),
+        usage: usage.to_string(),
+        description: description.to_string(),
+    };
+}
+
+fn exit_code_help(code: i32, description: &str) -> ExitCodeHelp {
+    return ExitCodeHelp {
+        code: code,
+        description: description.to_string(),
+    };
+}
+
+// Mirrors USAGE_TEXT, which should be kept in step with it
+pub fn help_result(api_version: &str) -> HelpResult {
+    return HelpResult {
+        api_version: api_version.to_string(),
+        options: vec![
+            flag_help("--user", &["-u"], "The OBJECT is the name of a user"),
+            flag_help("--group", &["-g"], "The OBJECT is the name of a group"),
+            flag_help("--uid", &[], "The OBJECT is the numeric ID of a user"),
+            flag_help("--gid", &[], "The OBJECT is the numeric ID of a group"),
+            flag_help(
+                "--is-member",
+                &[],
+                "Checks whether the first OBJECT (a user) is a member of the second OBJECT (a group)",
+            ),
+            flag_help("--quiet", &[], "Prints nothing; only the exit status reports the outcome"),
+            flag_help("--stdin", &[], "Reads additional OBJECTs from standard input, one per line"),
+            flag_help("--users", &[], "Lists all users instead of all groups"),
+            flag_help("--tree", &[], "Lists all groups, each followed by its users"),
+            flag_help(
+                "--by-user",
+                &[],
+                "With --tree, lists all users, each followed by their groups",
+            ),
+            flag_help(
+                "-l",
+                &[],
+                "Lists all groups (or all users, with --users) as an aligned table",
+            ),
+            option_help(
+                "--columns",
+                &[],
+                Some("COLUMN,..."),
+                &[],
+                "Lists only the given columns, in the given order, with -l",
+            ),
+            option_help(
+                "--sort",
+                &[],
+                Some("KEY"),
+                &["name", "id", "members"],
+                "Sorts the -l listing",
+            ),
+            flag_help("--reverse", &[], "Reverses the order of the -l listing"),
+            flag_help(
+                "--matrix",
+                &[],
+                "Lists all users by all groups, marking primary and supplementary memberships",
+            ),
+            option_help(
+                "--match-users",
+                &[],
+                Some("GLOB,..."),
+                &[],
+                "Narrows down the rows of --matrix to the users whose names match any of the globs",
+            ),
+            option_help(
+                "--match-groups",
+                &[],
+                Some("GLOB,..."),
+                &[],
+                "Narrows down the columns of --matrix to the groups whose names match any of the globs",
+            ),
+            flag_help(
+                "--by-name",
+                &[],
+                "Matches an all-digit OBJECT given with -u or -g by name only",
+            ),
+            flag_help("--help", &[], "Displays this help message"),
+            flag_help("--json", &[], "Formats the program output as JSON (the same as --format json)"),
+            option_help(
+                "--format",
+                &[],
+                Some("FORMAT"),
+                &OutputFormat::NAMES,
+                "Formats the program output",
+            ),
+            option_help(
+                "--template",
+                &[],
+                Some("TEMPLATE"),
+                &[],
+                "Prints each record laid out by TEMPLATE, in which `{FIELD}` stands for a CSV column (not a JSON key)",
+            ),
+            option_help(
+                "--html",
+                &[],
+                Some("FILE"),
+                &[],
+                "With `groupls report`, writes the HTML report to FILE (or to standard output if FILE is `-`)",
+            ),
+            flag_help(
+                "--primary-only",
+                &[],
+                "Only lists memberships by primary group ID",
+            ),
+            flag_help(
+                "--supplementary-only",
+                &[],
+                "Only lists memberships by being listed as a member of a group",
+            ),
+            flag_help(
+                "--strict",
+                &[],
+                "Fails if any line of the passwd, group or gshadow files cannot be parsed",
+            ),
+            flag_help(
+                "--explain",
+                &[],
+                "Follows each membership with the file and line that it comes from",
+            ),
+            flag_help(
+                "--shell-login-only",
+                &[],
+                "Leaves out users whose shell denies logins when listing users",
+            ),
+            option_help(
+                "--source",
+                &[],
+                Some("SOURCE"),
+                &["files", "userdb", "nss"],
+                "Where users and groups are read from",
+            ),
+            option_help(
+                "--root",
+                &[],
+                Some("DIR"),
+                &[],
+                "Reads the passwd and group files from under DIR instead of /",
+            ),
+            option_help(
+                "--passwd-file",
+                &[],
+                Some("FILE"),
+                &[],
+                "Reads users from FILE instead of /etc/passwd",
+            ),
+            option_help(
+                "--group-file",
+                &[],
+                Some("FILE"),
+                &[],
+                "Reads groups from FILE instead of /etc/group",
+            ),
+            option_help(
+                "--gshadow-file",
+                &[],
+                Some("FILE"),
+                &[],
+                "Reads group administrators and members from FILE instead of /etc/gshadow, failing if FILE cannot be read",
+            ),
+        ],
+        modes: vec![
+            mode_help("listGroups", "groupls", "Lists all groups"),
+            mode_help("listUsers", "groupls --users", "Lists all users"),
+            mode_help(
+                "groupTree",
+                "groupls --tree",
+                "Lists all groups, with the users of each group nested underneath",
+            ),
+            mode_help(
+                "matrix",
+                "groupls --matrix",
+                "Lists all users by all groups, marking primary and supplementary memberships",
+            ),
+            mode_help(
+                "userQuery",
+                "groupls -u USER",
+                "Lists the groups that a user is a member of",
+            ),
+            mode_help(
+                "groupQuery",
+                "groupls -g GROUP",
+                "Lists the users that belong to a group",
+            ),
+            mode_help(
+                "membershipQuery",
+                "groupls --is-member USER GROUP",
+                "Checks whether a user is a member of a group",
+            ),
+            mode_help(
+                "batch",
+                "groupls -u USER USER...",
+                "Lists the groups of each user (or the users of each group) in turn",
+            ),
+            mode_help(
+                "audit",
+                "groupls audit",
+                "Checks the users and groups for consistency problems",
+            ),
+            mode_help(
+                "compliance",
+                "groupls compliance",
+                "Runs CIS benchmark style checks on the users and groups",
+            ),
+            mode_help(
+                "report",
+                "groupls report --html FILE",
+                "Writes an HTML report of the groups, memberships, audit findings and users",
+            ),
+        ],
+        exit_codes: vec![
+            exit_code_help(0, "Success"),
+            exit_code_help(error_codes::INVALID_USAGE, "Invalid usage"),
+            exit_code_help(error_codes::READ_GROUPS_ERROR, "Groups could not be read"),
+            exit_code_help(error_codes::READ_USERS_ERROR, "Users could not be read"),
+            exit_code_help(
+                error_codes::READ_STDIN_ERROR,
+                "OBJECTs could not be read from standard input",
+            ),
+            exit_code_help(
+                error_codes::UNPARSEABLE_LINES,
+                "A line could not be parsed, and --strict is in effect",
+            ),
+            exit_code_help(error_codes::INTERNAL_ERROR, "Internal error"),
+            exit_code_help(error_codes::WRITE_ERROR, "The output file could not be written"),
+            exit_code_help(error_codes::GROUP_NOT_FOUND, "A group could not be found"),
+            exit_code_help(error_codes::USER_NOT_FOUND, "A user could not be found"),
+            exit_code_help(error_codes::NOT_A_MEMBER, "The user is not a member of the group"),
+            exit_code_help(error_codes::AUDIT_ERRORS_FOUND, "The audit found errors"),
+            exit_code_help(
+                error_codes::COMPLIANCE_CHECKS_FAILED,
+                "A compliance check failed",
+            ),
+        ],
+    };
+}
@@ -0,0 +1,222 @@
+// Contains the self-contained HTML report written by `groupls report --html FILE`: the group
+// overview, a sortable user by group membership matrix, the audit findings and the detail of
+// each user, in a single file with no external resources
+
+use crate::responses::{Membership, ReportQueryResult, UserDetail};
+
+const STYLE: &'static str = r#"
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; vertical-align: top; }
+th { background: #eee; cursor: pointer; user-select: none; }
+td.number { text-align: right; }
+.matrix td { text-align: center; }
+.matrix td.primary { background: #cde; }
+.matrix td.supplementary { background: #edc; }
+.matrix td.both { background: #cdc; }
+.error { color: #a00; }
+.warning { color: #a60; }
+details { margin-bottom: 0.5em; }
+"#;
+
+// Sorts a table by the clicked column, numerically if every value is a number, and in
+// reverse when clicked again
+const SCRIPT: &'static str = r#"
+document.querySelectorAll("table.sortable th").forEach(function (th) {
+  th.addEventListener("click", function () {
+    var table = th.closest("table");
+    var body = table.tBodies[0];
+    var rows = Array.prototype.slice.call(body.rows);
+    var index = Array.prototype.indexOf.call(th.parentNode.children, th);
+    var value = function (row) { return row.cells[index].textContent.trim(); };
+    var numeric = rows.every(function (row) { return value(row) === "" || !isNaN(value(row)); });
+    var ascending = th.dataset.order !== "ascending";
+    rows.sort(function (a, b) {
+      var x = value(a), y = value(b);
+      var order = numeric ? (Number(x) - Number(y)) : x.localeCompare(y);
+      return ascending ? order : -order;
+    });
+    th.parentNode.querySelectorAll("th").forEach(function (other) { delete other.dataset.order; });
+    th.dataset.order = ascending ? "ascending" : "descending";
+    rows.forEach(function (row) { body.appendChild(row); });
+  });
+});
+"#;
+
+fn escape(text: &str) -> String {
+    return text
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;");
+}
+
+fn membership_cell(membership: Option<Membership>) -> String {
+    match membership {
+        Some(Membership::Primary) => "<td class=\"primary\" title=\"primary\">P</td>".to_string(),
+        Some(Membership::Supplementary) => {
+            "<td class=\"supplementary\" title=\"supplementary\">S</td>".to_string()
+        }
+        Some(Membership::Both) => "<td class=\"both\" title=\"both\">P S</td>".to_string(),
+        None => "<td></td>".to_string(),
+    }
+}
+
+fn group_overview(report: &ReportQueryResult, html: &mut Vec<String>) {
+    html.push("<h2 id=\"groups\">Groups</h2>".to_string());
+    html.push("<table class=\"sortable\"><thead><tr><th>Group</th><th>GID</th><th>Members</th><th>Users</th></tr></thead><tbody>".to_string());
+    for group in report.groups.iter() {
+        let users: Vec<String> = group
+            .users
+            .iter()
+            .map(|user| format!("<a href=\"#user-{0}\">{0}</a>", escape(&user.name)))
+            .collect();
+        html.push(format!(
+            "<tr><td>{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td><td>{}</td></tr>",
+            escape(&group.name),
+            group.id,
+            group.users.len(),
+            users.join(", ")
+        ));
+    }
+    html.push("</tbody></table>".to_string());
+}
+
+// Users are rows and groups columns; P marks a primary group, and S a group listing the user
+fn membership_matrix(report: &ReportQueryResult, html: &mut Vec<String>) {
+    html.push("<h2 id=\"matrix\">Membership matrix</h2>".to_string());
+    html.push(
+        "<p><b>P</b>: primary group; <b>S</b>: listed as a member. Click a heading to sort.</p>"
+            .to_string(),
+    );
+    let headings: Vec<String> = report
+        .groups
+        .iter()
+        .map(|group| format!("<th>{}</th>", escape(&group.name)))
+        .collect();
+    html.push(format!(
+        "<table class=\"sortable matrix\"><thead><tr><th>User</th>{}</tr></thead><tbody>",
+        headings.join("")
+    ));
+    for user in report.users.iter() {
+        let cells: Vec<String> = report
+            .groups
+            .iter()
+            .map(|group| {
+                membership_cell(
+                    user.groups
+                        .iter()
+                        .find(|g| g.name == group.name && g.id == group.id)
+                        .and_then(|g| g.membership),
+                )
+            })
+            .collect();
+        html.push(format!(
+            "<tr><th><a href=\"#user-{0}\">{0}</a></th>{1}</tr>",
+            escape(&user.name),
+            cells.join("")
+        ));
+    }
+    html.push("</tbody></table>".to_string());
+}
+
+fn audit_findings(report: &ReportQueryResult, html: &mut Vec<String>) {
+    html.push("<h2 id=\"audit\">Audit findings</h2>".to_string());
+    if report.findings.is_empty() {
+        html.push("<p>No findings.</p>".to_string());
+        return;
+    }
+    html.push("<table class=\"sortable\"><thead><tr><th>Severity</th><th>Check</th><th>Finding</th><th>Locations</th></tr></thead><tbody>".to_string());
+    for finding in report.findings.iter() {
+        let locations: Vec<String> = finding
+            .locations
+            .iter()
+            .map(|location| escape(&location.to_string()))
+            .collect();
+        html.push(format!(
+            "<tr><td class=\"{0}\">{0}</td><td>{1}</td><td>{2}</td><td>{3}</td></tr>",
+            finding.severity,
+            escape(&finding.id),
+            escape(&finding.message),
+            locations.join("<br>")
+        ));
+    }
+    html.push("</tbody></table>".to_string());
+}
+
+fn user_detail(user: &UserDetail, html: &mut Vec<String>) {
+    html.push(format!(
+        "<details id=\"user-{0}\"><summary>{0} ({1})</summary><dl>",
+        escape(&user.name),
+        user.id
+    ));
+    let primary_group = match &user.primary_group {
+        Some(group) => format!("{} ({})", escape(group), user.primary_group_id),
+        None => format!("{} (no such group)", user.primary_group_id),
+    };
+    let fields = [
+        ("Full name", escape(&user.full_name)),
+        ("Primary group", primary_group),
+        ("Home directory", escape(&user.home_directory)),
+        ("Shell", escape(&user.shell)),
+    ];
+    for (name, value) in fields.iter() {
+        html.push(format!("<dt>{}</dt><dd>{}</dd>", name, value));
+    }
+    let groups: Vec<String> = user
+        .groups
+        .iter()
+        .map(|group| {
+            let marker = match group.membership {
+                Some(Membership::Primary) => " (primary)",
+                Some(Membership::Both) => " (primary, listed)",
+                _ => "",
+            };
+            format!("{}{}", escape(&group.name), marker)
+        })
+        .collect();
+    html.push(format!("<dt>Groups</dt><dd>{}</dd>", groups.join(", ")));
+    html.push("</dl></details>".to_string());
+}
+
+pub fn report(report: &ReportQueryResult) -> String {
+    let mut html: Vec<String> = vec![
+        "<!DOCTYPE html>".to_string(),
+        "<html lang=\"en\"><head><meta charset=\"utf-8\">".to_string(),
+        "<title>groupls access report</title>".to_string(),
+        format!("<style>{}</style>", STYLE),
+        "</head><body>".to_string(),
+        "<h1>Access report</h1>".to_string(),
+        format!(
+            "<p>{} groups and {} users, read from {}.</p>",
+            report.groups.len(),
+            report.users.len(),
+            escape(&report.source_files.join(", "))
+        ),
+    ];
+
+    if !report.warnings.is_empty() {
+        html.push("<h2 id=\"warnings\">Skipped lines</h2><ul>".to_string());
+        for warning in report.warnings.iter() {
+            html.push(format!(
+                "<li class=\"warning\">{}</li>",
+                escape(&warning.to_string())
+            ));
+        }
+        html.push("</ul>".to_string());
+    }
+
+    group_overview(report, &mut html);
+    membership_matrix(report, &mut html);
+    audit_findings(report, &mut html);
+
+    html.push("<h2 id=\"users\">Users</h2>".to_string());
+    for user in report.users.iter() {
+        user_detail(user, &mut html);
+    }
+
+    html.push(format!("<script>{}</script>", SCRIPT));
+    html.push("</body></html>".to_string());
+    return html.join("\n");
+}
@@ -1,70 +1,311 @@
-use crate::errors::Error;
-use crate::parse_system::{parse_group_line, parse_passwd_line, remove_comment_from_line};
-use crate::records::{GroupEntry, PasswdEntry};
+use crate::errors::{io_error, Error, Input};
+use crate::nss;
+use crate::parse_system::{
+    is_comment_line, is_legacy_entry, parse_group_line, parse_gshadow_line, parse_passwd_line,
+};
+use crate::records::{GShadowEntry, GroupEntry, PasswdEntry, Provenance, SkippedLine};
+use crate::userdb::{self, UserdbRecords, USERDB_DIRECTORIES};
+use std::cell::OnceCell;
 use std::fs;
-use std::path::Path;
+use std::io;
+use std::path::{Path, PathBuf};
 
 const GROUP_FILE: &'static str = "/etc/group";
+const GSHADOW_FILE: &'static str = "/etc/gshadow";
 const PASSWD_FILE: &'static str = "/etc/passwd";
 
-pub fn read_users() -> Result<Vec<PasswdEntry>, Box<dyn Error>> {
-    let contents =
-        fs::read_to_string(Path::new(PASSWD_FILE)).map_err(|e| Box::new(e) as Box<dyn Error>)?;
+// Locations of the system files that records are loaded from
+#[derive(Clone)]
+pub struct SourcePaths {
+    pub passwd_file: PathBuf,
+    pub group_file: PathBuf,
+    pub gshadow_file: Option<PathBuf>,
+    // Whether the gshadow file was given explicitly, so that failing to read it is an error
+    pub gshadow_file_required: bool,
+    pub userdb_directories: Vec<PathBuf>,
+}
 
-    let lines = contents.lines().into_iter();
+// Resolves one of the well-known system file paths, relative to `root` if one is given
+fn under_root(root: Option<&str>, file: &'static str) -> PathBuf {
+    match root {
+        Some(root) => Path::new(root).join(file.trim_start_matches('/')),
+        None => PathBuf::from(file),
+    }
+}
 
-    let lines_results = lines
-        .map(remove_comment_from_line)
-        .map(|line| line.trim())
-        .filter(|line| !line.is_empty())
-        .map(parse_passwd_line);
+impl SourcePaths {
+    // Explicitly given files are used as-is; they are not resolved relative to `root`.
+    // An explicit group file is not paired with the default gshadow file.
+    pub fn new(
+        root: Option<&str>,
+        passwd_file: Option<&str>,
+        group_file: Option<&str>,
+        gshadow_file: Option<&str>,
+    ) -> SourcePaths {
+        SourcePaths {
+            passwd_file: match passwd_file {
+                Some(path) => PathBuf::from(path),
+                None => under_root(root, PASSWD_FILE),
+            },
+            group_file: match group_file {
+                Some(path) => PathBuf::from(path),
+                None => under_root(root, GROUP_FILE),
+            },
+            gshadow_file: match (gshadow_file, group_file) {
+                (Some(path), _) => Some(PathBuf::from(path)),
+                (None, Some(_)) => None,
+                (None, None) => Some(under_root(root, GSHADOW_FILE)),
+            },
+            gshadow_file_required: gshadow_file.is_some(),
+            userdb_directories: USERDB_DIRECTORIES
+                .iter()
+                .map(|directory| under_root(root, directory))
+                .collect(),
+        }
+    }
+}
 
-    let mut line_errors = lines_results
-        .clone()
-        .filter_map(|result| match result {
-            Ok(_) => None,
-            Err(e) => Some(e),
-        })
-        .peekable();
+// Where user and group records are loaded from
+#[derive(Clone)]
+pub enum DataSource {
+    // Flat passwd and group files
+    Files(SourcePaths),
+    // Whatever the C library's Name Service Switch is configured to use
+    Nss,
+    // Flat passwd and group files, merged with systemd userdb JSON records. The records
+    // are read on first use, along with the directories they were read from, and shared
+    // by users and groups.
+    Userdb(SourcePaths, OnceCell<(UserdbRecords, Vec<String>)>),
+}
+
+impl DataSource {
+    pub fn userdb(paths: SourcePaths) -> DataSource {
+        return DataSource::Userdb(paths, OnceCell::new());
+    }
+
+    pub fn passwd_source_name(&self) -> String {
+        match self {
+            DataSource::Files(paths) | DataSource::Userdb(paths, _) => {
+                paths.passwd_file.display().to_string()
+            }
+            DataSource::Nss => "nss:passwd".to_string(),
+        }
+    }
+
+    pub fn group_source_name(&self) -> String {
+        match self {
+            DataSource::Files(paths) | DataSource::Userdb(paths, _) => {
+                paths.group_file.display().to_string()
+            }
+            DataSource::Nss => "nss:group".to_string(),
+        }
+    }
+
+    // Reads all users, recording the name of each source read in `source_files`
+    // and each line that could not be parsed in `skipped_lines`
+    pub fn read_users(
+        &self,
+        source_files: &mut Vec<String>,
+        skipped_lines: &mut Vec<SkippedLine>,
+    ) -> Result<Vec<PasswdEntry>, Error> {
+        match self {
+            DataSource::Files(paths) => {
+                let users = read_users(&paths.passwd_file, skipped_lines)?;
+                source_files.push(self.passwd_source_name());
+                return Ok(users);
+            }
+            DataSource::Nss => {
+                let users = nss::read_users()?;
+                source_files.push(self.passwd_source_name());
+                return Ok(users);
+            }
+            DataSource::Userdb(paths, userdb_records) => {
+                let mut users = read_users(&paths.passwd_file, skipped_lines)?;
+                source_files.push(self.passwd_source_name());
 
-    if let Some(_) = line_errors.peek() {
-        for error in line_errors {
-            eprintln!("{}", error);
-            eprintln!("Unparseable user entry encountered. Skipping...");
+                let (records, directories) = userdb_records
+                    .get_or_init(|| userdb::read_directories(&paths.userdb_directories));
+                skipped_lines.extend(records.skipped_users.iter().cloned());
+                userdb::merge_users(&mut users, records);
+                record_sources(source_files, directories.clone());
+                return Ok(users);
+            }
         }
     }
 
-    return Ok(lines_results.filter_map(Result::ok).collect());
+    // Finds legacy `+` entries in the passwd, group and gshadow files. NSS sources
+    // have no files of their own to look at, so None is returned for them.
+    pub fn read_legacy_entries(&self) -> Option<Vec<Provenance>> {
+        match self {
+            DataSource::Files(paths) | DataSource::Userdb(paths, _) => {
+                let mut entries = vec![];
+                let mut files = vec![&paths.passwd_file, &paths.group_file];
+                if let Some(gshadow_file) = &paths.gshadow_file {
+                    files.push(gshadow_file);
+                }
+                for file in files {
+                    // Unreadable files are reported when reading users and groups
+                    if let Ok(file_entries) = read_legacy_entries(file) {
+                        entries.extend(file_entries);
+                    }
+                }
+                return Some(entries);
+            }
+            DataSource::Nss => None,
+        }
+    }
+
+    // Reads all groups, recording the name of each source read in `source_files`
+    // and each line that could not be parsed in `skipped_lines`.
+    // The default gshadow file is optional, as it is usually only readable by root, but one
+    // given explicitly must be readable.
+    pub fn read_groups(
+        &self,
+        source_files: &mut Vec<String>,
+        skipped_lines: &mut Vec<SkippedLine>,
+    ) -> Result<Vec<GroupEntry>, Error> {
+        match self {
+            DataSource::Files(paths) | DataSource::Userdb(paths, _) => {
+                let mut groups = read_groups(&paths.group_file, skipped_lines)?;
+                source_files.push(self.group_source_name());
+
+                if let Some(gshadow_file) = &paths.gshadow_file {
+                    match read_gshadow(gshadow_file, skipped_lines) {
+                        Ok(gshadow_entries) => {
+                            merge_gshadow(&mut groups, gshadow_entries);
+                            source_files.push(gshadow_file.display().to_string());
+                        }
+                        Err(error) if paths.gshadow_file_required => return Err(error),
+                        Err(_) => {}
+                    }
+                }
+
+                if let DataSource::Userdb(_, userdb_records) = self {
+                    let (records, directories) = userdb_records
+                        .get_or_init(|| userdb::read_directories(&paths.userdb_directories));
+                    skipped_lines.extend(records.skipped_groups.iter().cloned());
+                    userdb::merge_groups(&mut groups, records);
+                    record_sources(source_files, directories.clone());
+                }
+                return Ok(groups);
+            }
+            DataSource::Nss => {
+                let groups = nss::read_groups()?;
+                source_files.push(self.group_source_name());
+                return Ok(groups);
+            }
+        }
+    }
 }
 
-pub fn read_groups<'a>() -> Result<Vec<GroupEntry>, Box<dyn Error>> {
-    let contents =
-        fs::read_to_string(Path::new(GROUP_FILE)).map_err(|e| Box::new(e) as Box<dyn Error>)?;
+// Adds `sources` to `source_files`, leaving out any that were already recorded
+fn record_sources(source_files: &mut Vec<String>, sources: Vec<String>) {
+    for source in sources {
+        if !source_files.contains(&source) {
+            source_files.push(source);
+        }
+    }
+}
 
-    let lines = contents.lines().into_iter();
+// Masks the password field of a passwd, group or gshadow line, so that a skipped line can
+// be reported without publishing its hash. Placeholders such as `x`, `*` and `!` are kept.
+fn redact_password(line: &str) -> String {
+    let mut fields: Vec<&str> = line.split(':').collect();
+    if fields.len() > 1 && !fields[1].chars().all(|c| c == 'x' || c == '*' || c == '!') {
+        fields[1] = "[redacted]";
+    }
+    return fields.join(":");
+}
 
-    let lines_results = lines
-        .map(remove_comment_from_line)
-        .map(|line| line.trim())
-        .filter(|line| !line.is_empty())
-        .map(parse_group_line);
+// Reads a colon-separated system file, skipping (and recording) any lines that cannot be parsed
+fn read_records<T>(
+    file: &Path,
+    parse_line: fn(&str, Provenance) -> Result<T, Error>,
+    input: Input,
+    skipped_lines: &mut Vec<SkippedLine>,
+) -> Result<Vec<T>, Error> {
+    let file_name = file.display().to_string();
+    let contents = fs::read_to_string(file).map_err(|e| io_error(input, file_name.clone(), e))?;
 
-    let mut line_errors = lines_results
-        .clone()
-        .filter_map(|result| match result {
-            Ok(_) => None,
-            Err(e) => Some(e),
-        })
-        .peekable();
+    let mut records = vec![];
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || is_comment_line(line) {
+            continue;
+        }
 
-    if let Some(_) = line_errors.peek() {
-        for error in line_errors {
-            eprintln!("{}", error);
-            eprintln!("Unparseable group entry encountered. Skipping...");
+        let provenance = Provenance {
+            file: file_name.clone(),
+            line: Some(i + 1),
+        };
+        match parse_line(line, provenance.clone()) {
+            Ok(record) => records.push(record),
+            Err(error) => {
+                skipped_lines.push(SkippedLine {
+                    provenance: provenance,
+                    text: redact_password(line),
+                    reason: error.reason(),
+                });
+            }
         }
     }
 
-    let results: std::vec::Vec<GroupEntry> = lines_results.filter_map(Result::ok).collect();
+    return Ok(records);
+}
+
+fn read_legacy_entries(file: &Path) -> Result<Vec<Provenance>, io::Error> {
+    let contents = fs::read_to_string(file)?;
 
-    Ok(results)
+    return Ok(contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| is_legacy_entry(line.trim()))
+        .map(|(i, _)| Provenance {
+            file: file.display().to_string(),
+            line: Some(i + 1),
+        })
+        .collect());
+}
+
+pub fn read_users(
+    passwd_file: &Path,
+    skipped_lines: &mut Vec<SkippedLine>,
+) -> Result<Vec<PasswdEntry>, Error> {
+    return read_records(passwd_file, parse_passwd_line, Input::Users, skipped_lines);
+}
+
+pub fn read_groups(
+    group_file: &Path,
+    skipped_lines: &mut Vec<SkippedLine>,
+) -> Result<Vec<GroupEntry>, Error> {
+    return read_records(group_file, parse_group_line, Input::Groups, skipped_lines);
+}
+
+pub fn read_gshadow(
+    gshadow_file: &Path,
+    skipped_lines: &mut Vec<SkippedLine>,
+) -> Result<Vec<GShadowEntry>, Error> {
+    return read_records(
+        gshadow_file,
+        parse_gshadow_line,
+        Input::Groups,
+        skipped_lines,
+    );
+}
+
+// Folds the administrators and members listed in /etc/gshadow into the matching groups
+pub fn merge_gshadow(groups: &mut [GroupEntry], gshadow_entries: Vec<GShadowEntry>) {
+    for gshadow_entry in gshadow_entries {
+        if let Some(group) = groups.iter_mut().find(|g| g.group == gshadow_entry.group) {
+            for username in gshadow_entry.usernames {
+                if !group.usernames.contains(&username) {
+                    group
+                        .member_provenance
+                        .push((username.clone(), gshadow_entry.provenance.clone()));
+                    group.usernames.push(username);
+                }
+            }
+            group.administrators = gshadow_entry.administrators;
+        }
+    }
 }
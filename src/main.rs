@@ -1,47 +1,192 @@
-#![feature(trait_alias)]
+// The codebase favours explicit `return` statements and `field: field` initialisers
+#![allow(
+    clippy::needless_return,
+    clippy::redundant_field_names,
+    clippy::redundant_static_lifetimes
+)]
 
 mod args;
+mod audit;
+mod compliance;
 mod errors;
+mod formatters;
+mod graph;
+mod help;
+mod html;
 mod load;
+mod markdown;
+mod nss;
 mod parse_system;
 mod records;
 mod responses;
+mod sarif;
 mod shapes;
+mod userdb;
 
 extern crate itertools;
 
-use std::collections::HashSet;
 use std::env;
+use std::io::{self, BufRead};
 use std::iter::Iterator;
 use std::process::exit;
 use std::result::Result::{Err, Ok};
 
-pub use args::{parse_argv_data, process_args, FlagArg, TargetObjects};
-pub use errors::Error;
-pub use records::{GroupEntry, PasswdEntry};
+pub use args::{
+    parse_argv_data, process_args, process_format_args, requested_format, Filters, FlagArg,
+    Listing, MembershipFilter, ObjectName, OptionArg, Subcommand, TargetObjects,
+};
+pub use errors::{io_error, Error, Input, ObjectKind};
+pub use records::{GroupEntry, PasswdEntry, SkippedLine};
 pub use responses::{
-    output_response, GroupOverviewQueryResult, GroupQueryResponse, GroupQueryResult,
-    NoResponseResult, TopLevelResponse, User, UserQueryResponse, UserQueryResult,
+    output_response, AuditQueryResult, BatchEntry, BatchResult, CheckStatus, ComplianceQueryResult,
+    GroupOverviewQueryResult, GroupQueryResponse, GroupQueryResult, GroupTreeNode,
+    GroupTreeQueryResult, MatrixAxis, MatrixCell, MatrixQueryResult, Membership,
+    MembershipQueryResult, NoResponseResult, OutputFormat, ReportQueryResult, TopLevelResponse,
+    User, UserDetail, UserOverviewQueryResult, UserQueryResponse, UserQueryResult, UserSummary,
+    UserTreeNode, UserTreeQueryResult, Warning,
 };
 pub use shapes::{IntToStringList, StringList, StringToStringList};
 
-const USAGE_TEXT: &'static str = r#"usage: groupls [--help] [-u | -g | --user | --group]
-        [--json] [--] <OBJECT>
+const USAGE_TEXT: &'static str = r#"usage: groupls [--help] [-u | -g | --user | --group | --uid | --gid]
+        [--by-name] [--users] [--tree [--by-user]] [--stdin]
+        [--matrix [--match-users GLOB,...] [--match-groups GLOB,...]]
+        [-l [--columns COLUMN,...] [--sort name | id | members] [--reverse]]
+        [--is-member] [--quiet] [--primary-only | --supplementary-only]
+        [--json | --format text | json | sarif | csv | tsv | ndjson | dot | mermaid
+         | markdown | --template TEMPLATE]
+        [--source files | --source userdb | --source nss]
+        [--root DIR] [--passwd-file FILE] [--group-file FILE]
+        [--gshadow-file FILE] [--shell-login-only] [--explain] [--strict]
+        [--] <OBJECT>...
+       groupls audit | compliance [--json] [--source ...] [--root DIR] [...]
+       groupls report --html FILE [--source ...] [--root DIR] [...]
 
 `groupls` allows you to explore group permissions.
 
 Supported options:
     -u, --user    Indicates that the OBJECT is the name of a user
     -g, --group   Indicates that the OBJECT is the name of a group
+    --uid         Indicates that the OBJECT is the numeric ID of a user
+    --gid         Indicates that the OBJECT is the numeric ID of a group
+    --is-member   Checks whether the first OBJECT (a user) is a member of the
+                  second OBJECT (a group), exiting with status 0 if so and 110
+                  if not
+    --quiet       Prints nothing; only the exit status reports the outcome
+    --stdin       Reads additional OBJECTs from standard input, one per line
+    --users       Lists all users instead of all groups
+    --tree        Lists all groups, each followed by its users
+    --tree --by-user
+                  Lists all users, each followed by their groups
+    -l            Lists all groups (or all users, with --users) as an aligned
+                  table with a header row, cut down to the width of the
+                  terminal. Groups are listed with their `name`, `id`, `count`
+                  of members and `members`; users with their `name`, `uid`,
+                  primary `group` and `shell`, and optionally their primary
+                  `gid` and number of supplementary `groups`.
+    --columns COLUMN,...
+                  Lists only the given columns, in the given order, with -l
+    --sort name | id | members
+                  Sorts the -l listing by name, by ID or by number of members
+                  (for users, by number of supplementary groups)
+    --reverse     Reverses the order of the -l listing
+    --matrix      Lists all users (rows) by all groups (columns), marking each
+                  cell with P for the user's primary group, S for a group that
+                  lists the user as a member, P+S for both and - for neither.
+                  With --json, lists the users, the groups and each marked
+                  cell, with the index of its user and group in those lists;
+                  with --format csv, a row per user and a column per group,
+                  headed group:NAME:GID.
+    --match-users GLOB,...
+                  Narrows down the rows of --matrix to the users whose names
+                  match any of the shell globs, e.g. 'a*,svc-?'
+    --match-groups GLOB,...
+                  Narrows down the columns of --matrix to the groups whose
+                  names match any of the shell globs
+    --by-name     Matches an all-digit OBJECT given with -u or -g by name only.
+                  (By default, an all-digit OBJECT that names no user or group
+                   is matched against user or group IDs instead.)
     --help        Displays this help message
     --json        Indicates that the program output should be formatted as JSON.
-                  (Note that this is not supported when displaying this help message
-                   or error messages.)
+                  With --json, this help message is printed as a JSON description
+                  of the options, invocation forms and exit statuses, and usage
+                  errors are printed as a JSON object with `exitCode` 10.
+    --format FORMAT
+                  Formats the program output as `text` (default), `json` (the
+                  same as --json) or `sarif`, a SARIF 2.1.0 log of the lines
+                  that could not be parsed and of any audit or compliance
+                  findings, pointing into the passwd and group files. `csv` and
+                  `tsv` print one row per listed group, user, membership or
+                  finding under a header row, and `ndjson` prints the same
+                  records as one JSON object per line. `dot` (Graphviz) and
+                  `mermaid` draw the graph of users and groups, with bold or
+                  thick edges to primary groups and dashed or dotted edges to
+                  groups that list the user as a member: the whole graph for
+                  the group and user listings, or the subgraph around each
+                  user or group given with -u or -g. Users without any
+                  membership are only drawn for --users, and groups without
+                  members only for the group listing. `markdown` prints each
+                  listing as a Markdown table under a heading, with a table
+                  per group (or user) for --tree, for pull requests and wikis.
+    --template TEMPLATE
+                  Prints each record (as printed by --format csv) on a line of
+                  its own, laid out by TEMPLATE, in which `{FIELD}` stands for
+                  the value of the column called FIELD, e.g. '{name}:{id}'.
+                  The fields are the CSV column headers, not the JSON keys.
+                  `{{` and `}}` stand for literal braces, and `\t`, `\n`, `\0`
+                  and `\\` for a tab, newline, NUL and backslash. A template
+                  ending with `\0` ends each record with a NUL instead of a
+                  newline.
+    --html FILE   With `groupls report`, writes the HTML report to FILE (or to
+                  standard output if FILE is `-`). Exits with status 80 if
+                  FILE cannot be written.
+    --primary-only
+                  Only lists memberships by primary group ID (from the passwd file)
+    --supplementary-only
+                  Only lists memberships by being listed as a member of a group.
+                  (In text output, memberships by primary group ID are marked
+                   with `(primary)`, or `(both)` if the user is also listed.)
+    --strict      Fails with exit status 60 if any line of the passwd, group or
+                  gshadow files (or any userdb record) cannot be parsed. Such
+                  lines are otherwise skipped, with a warning on stderr (or in
+                  the `warnings` array of JSON output).
+    --explain     Follows each membership with the file and line of the passwd
+                  entry (for a primary group) and of the group entry (for a
+                  listed member) that it comes from
+    --shell-login-only
+                  Leaves out users whose shell denies logins (such as nologin or
+                  false) when listing users
+    --source files
+                  Reads users and groups from the passwd and group files (default)
+    --source userdb
+                  Reads the passwd and group files, merged with the systemd userdb
+                  JSON records in /etc/userdb, /run/userdb and /usr/lib/userdb
+    --source nss  Reads users and groups through the system's Name Service Switch,
+                  so that every database configured in /etc/nsswitch.conf is used
+    --root DIR    Reads the passwd and group files from under DIR instead of /,
+                  e.g. from a mounted disk image or a chroot
+    --passwd-file FILE
+                  Reads users from FILE instead of /etc/passwd
+    --group-file FILE
+                  Reads groups from FILE instead of /etc/group
+    --gshadow-file FILE
+                  Reads group administrators and members from FILE instead of
+                  /etc/gshadow, failing with exit status 30 if FILE cannot be
+                  read. (/etc/gshadow itself is skipped if it cannot be read,
+                   or if --group-file is given without --gshadow-file.)
+                  (Explicit file paths are not resolved relative to --root.)
     
-Three invocation forms are supported:
+Eleven invocation forms are supported:
 
     groupls
         - prints a list of all groups on this system.
+
+    groupls --users
+        - prints a list of all users on this system, with their user ID,
+          primary group and number of supplementary groups.
+
+    groupls --tree
+        - prints a tree of all groups on this system with the users of each
+          group (whether primary or supplementary) nested underneath.
     
     groupls -u alice
         - prints a list of all groups that the user called alice is a member of.
@@ -49,6 +194,40 @@ Three invocation forms are supported:
     groupls -g admin
         - prints a list of all users that belong to the group called admin.
 
+    groupls --matrix --match-groups 'wheel,sudo,adm*'
+        - prints a grid of all users by the groups called wheel, sudo or
+          starting with adm, marking primary and supplementary memberships.
+
+    groupls --is-member alice admin
+        - exits with status 0 if the user called alice is a member of the
+          group called admin, and with status 110 otherwise.
+
+    groupls -u alice bob
+    groupls -u --stdin < users.txt
+        - prints the groups of each user in turn, under a heading naming the
+          user. With --json, prints one JSON document per line instead.
+          Users that cannot be found are reported without stopping the batch.
+
+    groupls audit
+        - checks the users and groups on this system for duplicate names and
+          IDs, primary groups that do not exist, members that do not exist,
+          empty groups and more than one account with UID 0. Prints one
+          finding per problem, and exits with status 120 if any of them is
+          an error.
+
+    groupls compliance
+        - runs CIS benchmark style checks on the users and groups of this
+          system (all primary groups exist, no duplicate UIDs, GIDs or names,
+          root is the only UID 0 account, the shadow group is empty and there
+          are no legacy `+` entries), printing PASS, FAIL or SKIP for each
+          check with a remediation hint for those that fail. Exits with
+          status 121 if any check fails.
+
+    groupls report --html report.html
+        - writes a single self-contained HTML page for reviewers, with the
+          group overview, a sortable user by group membership matrix, the
+          audit findings and the detail of each user.
+
 Untrusted input:
 
     If invoking `groupls` with untrusted input, be sure to separate the option
@@ -64,6 +243,8 @@ Untrusted input:
     interfere with the output formatting.
 "#;
 
+const API_VERSION: &'static str = "1.0";
+
 mod error_codes {
     pub const INVALID_USAGE: i32 = 10;
 
@@ -71,158 +252,637 @@ mod error_codes {
 
     pub const READ_USERS_ERROR: i32 = 40;
 
+    pub const READ_STDIN_ERROR: i32 = 50;
+
+    pub const UNPARSEABLE_LINES: i32 = 60;
+
+    pub const INTERNAL_ERROR: i32 = 70;
+
+    pub const WRITE_ERROR: i32 = 80;
+
     pub const GROUP_NOT_FOUND: i32 = 100;
     pub const USER_NOT_FOUND: i32 = 101;
+
+    pub const NOT_A_MEMBER: i32 = 110;
+
+    pub const AUDIT_ERRORS_FOUND: i32 = 120;
+    pub const COMPLIANCE_CHECKS_FAILED: i32 = 121;
+}
+
+// How `user` belongs to `group`: by primary group ID, by being listed as a member, or both
+fn membership(user: &PasswdEntry, group: &GroupEntry) -> Option<Membership> {
+    let is_primary = group.group_id == user.primary_group_id;
+    let is_supplementary = group.usernames.contains(&user.user);
+
+    match (is_primary, is_supplementary) {
+        (true, true) => Some(Membership::Both),
+        (true, false) => Some(Membership::Primary),
+        (false, true) => Some(Membership::Supplementary),
+        (false, false) => None,
+    }
+}
+
+// How `user` belongs to `group`, if at all in a way that `filters` lets through
+fn filtered_membership(
+    user: &PasswdEntry,
+    group: &GroupEntry,
+    filters: Filters,
+) -> Option<Membership> {
+    return membership(user, group).filter(|membership| match filters.membership {
+        MembershipFilter::All => true,
+        MembershipFilter::PrimaryOnly => *membership != Membership::Supplementary,
+        MembershipFilter::SupplementaryOnly => *membership != Membership::Primary,
+    });
+}
+
+// The entries behind a membership: the passwd entry for a primary group, and the
+// entry listing the user as a member for a supplementary one
+fn membership_provenance(
+    user: &PasswdEntry,
+    group: &GroupEntry,
+    membership: Membership,
+) -> responses::Provenance {
+    let by_primary = membership != Membership::Supplementary;
+    let by_listing = membership != Membership::Primary;
+
+    return responses::Provenance {
+        user: Some(responses::Location::of(&user.provenance)).filter(|_| by_primary),
+        group: Some(responses::Location::of(group.member_provenance(&user.user)))
+            .filter(|_| by_listing),
+    };
+}
+
+// Resolves the groups of `user`, whether primary or supplementary
+fn user_groups(
+    user: &PasswdEntry,
+    groups: &[GroupEntry],
+    filters: Filters,
+    explain: bool,
+) -> Vec<responses::Group> {
+    return groups
+        .iter()
+        .filter_map(|group| {
+            filtered_membership(user, group, filters).map(|membership| responses::Group {
+                name: group.group.clone(),
+                id: group.group_id,
+                membership: Some(membership),
+                provenance: Some(membership)
+                    .filter(|_| explain)
+                    .map(|membership| membership_provenance(user, group, membership)),
+                members: None,
+            })
+        })
+        .collect();
+}
+
+// Resolves the users of `group`, whether it is their primary group or they are listed as members
+fn group_users(
+    group: &GroupEntry,
+    users: &[PasswdEntry],
+    filters: Filters,
+    explain: bool,
+) -> Vec<responses::User> {
+    let mut response_users: Vec<responses::User> = vec![];
+    for user in users {
+        if filters.login_shell_only && !user.has_login_shell() {
+            continue;
+        }
+
+        if let Some(membership) = filtered_membership(user, group, filters) {
+            let role = if group.administrators.contains(&user.user) {
+                responses::Role::Administrator
+            } else if user.primary_group_id == group.group_id {
+                responses::Role::Primary
+            } else {
+                responses::Role::Member
+            };
+
+            response_users.push(responses::User {
+                name: user.user.clone(),
+                id: user.user_id,
+                role: role,
+                membership: membership,
+                full_name: user.full_name(),
+                home_directory: user.home_directory.clone(),
+                shell: user.shell.clone(),
+                provenance: Some(membership)
+                    .filter(|_| explain)
+                    .map(|membership| membership_provenance(user, group, membership)),
+            });
+        }
+    }
+    return response_users;
+}
+
+fn detail_user(user: &PasswdEntry, groups: &[GroupEntry], filters: Filters) -> UserDetail {
+    let primary_group = groups
+        .iter()
+        .find(|group| group.group_id == user.primary_group_id);
+
+    return UserDetail {
+        name: user.user.clone(),
+        id: user.user_id,
+        primary_group_id: user.primary_group_id,
+        primary_group: primary_group.map(|group| group.group.clone()),
+        full_name: user.full_name(),
+        home_directory: user.home_directory.clone(),
+        shell: user.shell.clone(),
+        groups: user_groups(user, groups, filters, false),
+    };
+}
+
+fn summarize_user(user: &PasswdEntry, groups: &[GroupEntry]) -> UserSummary {
+    let primary_group = groups
+        .iter()
+        .find(|group| group.group_id == user.primary_group_id);
+    let supplementary_group_count = groups
+        .iter()
+        .filter(|group| {
+            group.group_id != user.primary_group_id && group.usernames.contains(&user.user)
+        })
+        .count();
+
+    return UserSummary {
+        name: user.user.clone(),
+        id: user.user_id,
+        primary_group_id: user.primary_group_id,
+        primary_group: primary_group.map(|group| group.group.clone()),
+        supplementary_group_count: supplementary_group_count,
+        shell: user.shell.clone(),
+    };
+}
+
+// What every query of a user, a group or a membership shares, whichever objects it is for
+struct QueryContext<'a> {
+    api_version: &'a str,
+    source_files: &'a [String],
+    warnings: &'a [Warning],
+    filters: Filters,
+    explain: bool,
+}
+
+impl<'a> QueryContext<'a> {
+    fn not_found(&self, kind: ObjectKind, object: &ObjectName) -> TopLevelResponse {
+        let error = Error::NotFound {
+            kind: kind,
+            object: object.describe(),
+        };
+        return TopLevelResponse::NoResponse(NoResponseResult::of(
+            self.api_version,
+            &error,
+            self.warnings.to_vec(),
+        ));
+    }
+}
+
+fn query_user(
+    user_object: &ObjectName,
+    users: &[PasswdEntry],
+    groups: &[GroupEntry],
+    context: &QueryContext,
+) -> TopLevelResponse {
+    let found_user = user_object.find(users, |u| &u.user, |u| u.user_id);
+    match found_user {
+        Some(found_user) => {
+            return TopLevelResponse::UserQuery(UserQueryResult {
+                api_version: context.api_version.to_string(),
+                source_files: context.source_files.to_vec(),
+                warnings: context.warnings.to_vec(),
+                user: UserQueryResponse {
+                    user_name: found_user.user.clone(),
+                    user_id: found_user.user_id,
+                    groups: user_groups(found_user, groups, context.filters, context.explain),
+                },
+            });
+        }
+        None => return context.not_found(ObjectKind::User, user_object),
+    }
+}
+
+fn query_group(
+    group_object: &ObjectName,
+    users: &[PasswdEntry],
+    groups: &[GroupEntry],
+    context: &QueryContext,
+) -> TopLevelResponse {
+    let found_group = group_object.find(groups, |g| &g.group, |g| g.group_id);
+    match found_group {
+        Some(found_group) => {
+            return TopLevelResponse::GroupQuery(responses::GroupQueryResult {
+                api_version: context.api_version.to_string(),
+                source_files: context.source_files.to_vec(),
+                warnings: context.warnings.to_vec(),
+                group: responses::GroupQueryResponse {
+                    group_name: found_group.group.clone(),
+                    group_id: found_group.group_id,
+                    users: group_users(found_group, users, context.filters, context.explain),
+                    administrators: found_group.administrators.clone(),
+                },
+            });
+        }
+        None => return context.not_found(ObjectKind::Group, group_object),
+    }
+}
+
+fn query_membership(
+    user_object: &ObjectName,
+    group_object: &ObjectName,
+    users: &[PasswdEntry],
+    groups: &[GroupEntry],
+    context: &QueryContext,
+) -> TopLevelResponse {
+    let found_user = user_object.find(users, |u| &u.user, |u| u.user_id);
+    let found_group = group_object.find(groups, |g| &g.group, |g| g.group_id);
+    match (found_user, found_group) {
+        (Some(found_user), Some(found_group)) => {
+            let membership = filtered_membership(found_user, found_group, context.filters);
+            return TopLevelResponse::MembershipQuery(MembershipQueryResult {
+                api_version: context.api_version.to_string(),
+                source_files: context.source_files.to_vec(),
+                warnings: context.warnings.to_vec(),
+                user_name: found_user.user.clone(),
+                group_name: found_group.group.clone(),
+                is_member: membership.is_some(),
+                provenance: membership
+                    .filter(|_| context.explain)
+                    .map(|membership| membership_provenance(found_user, found_group, membership)),
+            });
+        }
+        (None, _) => return context.not_found(ObjectKind::User, user_object),
+        (_, None) => return context.not_found(ObjectKind::Group, group_object),
+    }
+}
+
+fn collect_warnings(skipped_lines: &[SkippedLine]) -> Vec<Warning> {
+    return skipped_lines.iter().map(Warning::of).collect();
+}
+
+// With --strict, any line that could not be parsed fails the invocation, whatever the response
+fn strict_response(response: TopLevelResponse) -> TopLevelResponse {
+    let warnings = response.warnings().to_vec();
+    if warnings.is_empty() {
+        return response;
+    }
+
+    let first = &warnings[0];
+    let error = Error::Parse {
+        file: first.file.clone(),
+        line: first.line,
+        reason: format!(
+            "{}; {} line(s) could not be parsed, and --strict is in effect",
+            first.reason,
+            warnings.len()
+        ),
+    };
+    return TopLevelResponse::NoResponse(NoResponseResult::of(API_VERSION, &error, warnings));
 }
 
 fn groupls(target_objects: TargetObjects) -> TopLevelResponse {
-    let user_to_list = target_objects.user_to_list;
-    let group_to_list = target_objects.group_to_list;
+    let users_to_list = target_objects.users_to_list;
+    let groups_to_list = target_objects.groups_to_list;
+    let batch = target_objects.batch;
+    let membership_to_check = target_objects.membership_to_check;
+    let listing = target_objects.listing;
+    let subcommand = target_objects.subcommand;
+    let source = target_objects.source;
+    let filters = target_objects.filters;
+    let explain = target_objects.explain;
+    let list_members = target_objects.list_members;
+    let matrix_filter = target_objects.matrix_filter;
 
-    let groups_raw = load::read_groups();
+    let mut source_files: Vec<String> = vec![];
+    let mut skipped_lines: Vec<SkippedLine> = vec![];
+    let groups_raw = source.read_groups(&mut source_files, &mut skipped_lines);
 
-    let api_version = "1.0".to_string();
+    let api_version = API_VERSION.to_string();
 
     match groups_raw {
-        Err(error) => TopLevelResponse::NoResponse(NoResponseResult {
-            api_version: api_version,
-            error: format!("Could not read groups: {}", error),
-            exit_code: error_codes::READ_GROUPS_ERROR,
-        }),
+        Err(error) => TopLevelResponse::NoResponse(NoResponseResult::of(
+            &api_version,
+            &error,
+            collect_warnings(&skipped_lines),
+        )),
         Ok(groups) => {
-            match (user_to_list.clone(), group_to_list.clone()) {
-                (None, None) => {
-                    return TopLevelResponse::GroupOverview(GroupOverviewQueryResult {
-                        api_version: api_version,
-                        groups: groups
-                            .iter()
-                            .map(|record| {
-                                return responses::Group {
-                                    name: record.group.clone(),
-                                    id: record.group_id,
-                                };
-                            })
-                            .collect(),
-                    });
-                }
-                _ => {}
+            let warnings = collect_warnings(&skipped_lines);
+            let no_objects = users_to_list.is_empty()
+                && groups_to_list.is_empty()
+                && membership_to_check.is_none();
+            // The members of each group can only be listed once users are read
+            let is_group_listing = no_objects && !batch && listing == Listing::Groups;
+            if is_group_listing && subcommand.is_none() && !list_members {
+                return TopLevelResponse::GroupOverview(GroupOverviewQueryResult {
+                    api_version: api_version,
+                    source_files: source_files,
+                    warnings: warnings,
+                    groups: groups
+                        .iter()
+                        .map(|record| {
+                            return responses::Group {
+                                name: record.group.clone(),
+                                id: record.group_id,
+                                membership: None,
+                                provenance: None,
+                                members: None,
+                            };
+                        })
+                        .collect(),
+                });
             }
 
-            let users_raw = load::read_users();
+            let users_raw = source.read_users(&mut source_files, &mut skipped_lines);
+            let warnings = collect_warnings(&skipped_lines);
             match users_raw {
                 Err(error) => {
-                    return TopLevelResponse::NoResponse(NoResponseResult {
-                        api_version: api_version,
-                        error: format!("Could not read users: {}", error),
-                        exit_code: error_codes::READ_USERS_ERROR,
-                    });
+                    return TopLevelResponse::NoResponse(NoResponseResult::of(
+                        &api_version,
+                        &error,
+                        warnings,
+                    ));
                 }
                 Ok(users) => {
-                    if let Some(user_name) = user_to_list {
-                        let found_user = users.iter().find(|u| u.user == user_name);
-                        match found_user {
-                            Some(found_user) => {
-                                let primary_group_id = found_user.primary_group_id;
-                                let mut response_groups = vec![];
-
-                                for group in groups {
-                                    if group.group_id == primary_group_id
-                                        || group.usernames.iter().any(|u| *u == user_name)
-                                    {
-                                        response_groups.push(responses::Group {
-                                            name: group.group,
+                    match subcommand {
+                        Some(Subcommand::Audit) => {
+                            return TopLevelResponse::Audit(AuditQueryResult {
+                                api_version: api_version,
+                                source_files: source_files,
+                                warnings: warnings,
+                                findings: audit::audit(&users, &groups),
+                            });
+                        }
+                        Some(Subcommand::Compliance) => {
+                            let checks =
+                                compliance::check(&users, &groups, source.read_legacy_entries());
+                            return TopLevelResponse::Compliance(ComplianceQueryResult {
+                                api_version: api_version,
+                                source_files: source_files,
+                                warnings: warnings,
+                                passed: checks
+                                    .iter()
+                                    .all(|check| check.status != CheckStatus::Fail),
+                                checks: checks,
+                            });
+                        }
+                        Some(Subcommand::Report) => {
+                            return TopLevelResponse::Report(ReportQueryResult {
+                                api_version: api_version,
+                                source_files: source_files,
+                                warnings: warnings,
+                                groups: groups
+                                    .iter()
+                                    .map(|group| GroupTreeNode {
+                                        name: group.group.clone(),
+                                        id: group.group_id,
+                                        users: group_users(group, &users, filters, false),
+                                    })
+                                    .collect(),
+                                users: users
+                                    .iter()
+                                    .filter(|user| {
+                                        !filters.login_shell_only || user.has_login_shell()
+                                    })
+                                    .map(|user| detail_user(user, &groups, filters))
+                                    .collect(),
+                                findings: audit::audit(&users, &groups),
+                            });
+                        }
+                        None => {}
+                    }
+
+                    if no_objects && !batch {
+                        let listed_users = users
+                            .iter()
+                            .filter(|user| !filters.login_shell_only || user.has_login_shell());
+
+                        match listing {
+                            Listing::Users => {
+                                return TopLevelResponse::UserOverview(UserOverviewQueryResult {
+                                    api_version: api_version,
+                                    source_files: source_files,
+                                    warnings: warnings,
+                                    users: listed_users
+                                        .map(|user| summarize_user(user, &groups))
+                                        .collect(),
+                                });
+                            }
+                            Listing::GroupTree => {
+                                return TopLevelResponse::GroupTree(GroupTreeQueryResult {
+                                    api_version: api_version,
+                                    source_files: source_files,
+                                    warnings: warnings,
+                                    groups: groups
+                                        .iter()
+                                        .map(|group| GroupTreeNode {
+                                            name: group.group.clone(),
                                             id: group.group_id,
-                                        });
+                                            users: group_users(group, &users, filters, explain),
+                                        })
+                                        .collect(),
+                                });
+                            }
+                            Listing::UserTree => {
+                                return TopLevelResponse::UserTree(UserTreeQueryResult {
+                                    api_version: api_version,
+                                    source_files: source_files,
+                                    warnings: warnings,
+                                    users: listed_users
+                                        .map(|user| UserTreeNode {
+                                            name: user.user.clone(),
+                                            id: user.user_id,
+                                            groups: user_groups(user, &groups, filters, explain),
+                                        })
+                                        .collect(),
+                                });
+                            }
+                            Listing::Matrix => {
+                                let matrix_users: Vec<&PasswdEntry> = listed_users
+                                    .filter(|user| matrix_filter.matches_user(&user.user))
+                                    .collect();
+                                let matrix_groups: Vec<&GroupEntry> = groups
+                                    .iter()
+                                    .filter(|group| matrix_filter.matches_group(&group.group))
+                                    .collect();
+                                let mut memberships: Vec<MatrixCell> = vec![];
+                                for (u, user) in matrix_users.iter().enumerate() {
+                                    for (g, group) in matrix_groups.iter().enumerate() {
+                                        if let Some(membership) =
+                                            filtered_membership(user, group, filters)
+                                        {
+                                            memberships.push(MatrixCell {
+                                                user: user.user.clone(),
+                                                user_index: u,
+                                                group: group.group.clone(),
+                                                group_index: g,
+                                                membership: membership,
+                                            });
+                                        }
                                     }
                                 }
-
-                                return TopLevelResponse::UserQuery(UserQueryResult {
+                                return TopLevelResponse::Matrix(MatrixQueryResult {
                                     api_version: api_version,
-                                    user: UserQueryResponse {
-                                        user_name: user_name,
-                                        groups: response_groups,
-                                    },
+                                    source_files: source_files,
+                                    warnings: warnings,
+                                    users: matrix_users
+                                        .iter()
+                                        .map(|user| MatrixAxis {
+                                            name: user.user.clone(),
+                                            id: user.user_id,
+                                        })
+                                        .collect(),
+                                    groups: matrix_groups
+                                        .iter()
+                                        .map(|group| MatrixAxis {
+                                            name: group.group.clone(),
+                                            id: group.group_id,
+                                        })
+                                        .collect(),
+                                    memberships: memberships,
                                 });
                             }
-                            None => {
-                                return TopLevelResponse::NoResponse(NoResponseResult {
+                            Listing::Groups => {
+                                return TopLevelResponse::GroupOverview(GroupOverviewQueryResult {
                                     api_version: api_version,
-                                    error: format!("Could not find user: {}", user_name),
-                                    exit_code: error_codes::USER_NOT_FOUND,
+                                    source_files: source_files,
+                                    warnings: warnings,
+                                    groups: groups
+                                        .iter()
+                                        .map(|group| responses::Group {
+                                            name: group.group.clone(),
+                                            id: group.group_id,
+                                            membership: None,
+                                            provenance: None,
+                                            members: Some(
+                                                group_users(group, &users, filters, false)
+                                                    .into_iter()
+                                                    .map(|user| user.name)
+                                                    .collect(),
+                                            ),
+                                        })
+                                        .collect(),
                                 });
                             }
                         }
                     }
 
-                    let group_name = group_to_list.expect("group_to_list was None");
-                    let found_group = groups.iter().find(|g| g.group == group_name);
-                    match found_group {
-                        Some(found_group) => {
-                            let mut group_usernames: HashSet<String> = HashSet::new();
-                            for username in found_group.usernames.iter() {
-                                group_usernames.insert(username.clone());
-                            }
+                    let context = QueryContext {
+                        api_version: &api_version,
+                        source_files: &source_files,
+                        warnings: &warnings,
+                        filters: filters,
+                        explain: explain,
+                    };
+                    if let Some((user_object, group_object)) = membership_to_check {
+                        return query_membership(
+                            &user_object,
+                            &group_object,
+                            &users,
+                            &groups,
+                            &context,
+                        );
+                    }
 
-                            let mut response_users: Vec<responses::User> = vec![];
-                            for user in users {
-                                if user.primary_group_id == found_group.group_id
-                                    || group_usernames.contains(&user.user)
-                                {
-                                    response_users.push(responses::User {
-                                        name: user.user,
-                                        id: user.user_id,
-                                    });
-                                }
-                            }
+                    let mut entries: Vec<BatchEntry> = vec![];
+                    for user_object in users_to_list.iter() {
+                        entries.push(BatchEntry {
+                            object: user_object.describe(),
+                            response: query_user(user_object, &users, &groups, &context),
+                        });
+                    }
+                    for group_object in groups_to_list.iter() {
+                        entries.push(BatchEntry {
+                            object: group_object.describe(),
+                            response: query_group(group_object, &users, &groups, &context),
+                        });
+                    }
 
-                            return TopLevelResponse::GroupQuery(responses::GroupQueryResult {
-                                api_version: api_version,
-                                group: responses::GroupQueryResponse {
-                                    group_name: group_name,
-                                    users: response_users,
-                                },
-                            });
-                        }
-                        None => {
-                            return TopLevelResponse::NoResponse(NoResponseResult {
-                                api_version: api_version,
-                                error: format!("Could not find group: {}", group_name),
-                                exit_code: error_codes::GROUP_NOT_FOUND,
-                            });
-                        }
+                    if !batch {
+                        return entries.remove(0).response;
                     }
+
+                    return TopLevelResponse::Batch(BatchResult { entries: entries });
                 }
             }
         }
     }
 }
 
+// Usage errors are printed as an error object in JSON, SARIF and NDJSON output, and with a
+// pointer to the help message otherwise
+fn usage_failure(error: Error, format: OutputFormat) -> ! {
+    if !format.reports_errors_as_json() {
+        eprintln!(
+            "Usage error: {}.\n\nFor usage help, try: groupls --help",
+            error
+        );
+        exit(error.exit_code());
+    }
+    let result = NoResponseResult::of(API_VERSION, &error, vec![]);
+    output_response(TopLevelResponse::NoResponse(result), format);
+}
+
 fn main() {
     let args: Vec<String> = env::args().skip(1).collect();
+    let requested_format = requested_format(&args);
     let argv_data = parse_argv_data(args);
 
     match argv_data {
-        Err(e) => {
-            eprintln!("Usage error: {}.\n\nFor usage help, try: groupls --help", e);
-            exit(error_codes::INVALID_USAGE)
-        }
-        Ok((flag_args, pos_args)) => {
+        Err(e) => usage_failure(e, requested_format),
+        Ok((flag_args, option_args, mut pos_args)) => {
             if flag_args.contains(&FlagArg::HELP) {
-                eprintln!("{}", USAGE_TEXT);
+                if requested_format == OutputFormat::Json {
+                    let help = help::help_result(API_VERSION);
+                    let json = serde_json::to_string(&help).expect("Could not stringify JSON");
+                    println!("{}", json);
+                } else {
+                    eprintln!("{}", USAGE_TEXT);
+                }
                 exit(0);
             }
 
-            let is_json = flag_args.contains(&FlagArg::JSON);
-            let processed_args = process_args(flag_args, pos_args);
+            let format = match process_format_args(&flag_args, &option_args) {
+                Ok(format) => format,
+                Err(e) => usage_failure(e, requested_format),
+            };
+            let is_quiet = flag_args.contains(&FlagArg::QUIET);
+            let is_strict = flag_args.contains(&FlagArg::STRICT);
+
+            // Objects are only read once it is clear what kind of object they are,
+            // so that a misused --stdin is reported without waiting for input
+            let object_flags = [FlagArg::USER, FlagArg::UID, FlagArg::GROUP, FlagArg::GID];
+            if flag_args.contains(&FlagArg::STDIN)
+                && object_flags.iter().any(|flag| flag_args.contains(flag))
+            {
+                for line in io::stdin().lock().lines() {
+                    match line {
+                        Ok(line) if !line.trim().is_empty() => {
+                            pos_args.push(line.trim().to_string())
+                        }
+                        Ok(_) => {}
+                        Err(e) => {
+                            let error = io_error(Input::Objects, "stdin".to_string(), e);
+                            let result = NoResponseResult::of(API_VERSION, &error, vec![]);
+                            output_response(TopLevelResponse::NoResponse(result), format);
+                        }
+                    }
+                }
+            }
+
+            let processed_args = process_args(flag_args, option_args, pos_args);
 
             match processed_args {
                 Ok(target_objects) => {
-                    output_response(groupls(target_objects), is_json);
-                }
-                Err(e) => {
-                    eprintln!("Usage error: {}.\n\nFor usage help, try: groupls --help", e);
-                    exit(error_codes::INVALID_USAGE)
+                    let mut response = groupls(target_objects);
+                    if is_strict {
+                        response = strict_response(response);
+                    }
+                    if is_quiet {
+                        exit(response.exit_code());
+                    }
+                    if let Err(e) = format.check(&response) {
+                        usage_failure(e, format);
+                    }
+                    output_response(response, format);
                 }
+                Err(e) => usage_failure(e, format),
             };
         }
     }
@@ -0,0 +1,153 @@
+// Contains the Markdown rendering of each response, as tables under headings, for pasting
+// into pull requests and wiki pages
+
+use serde_json::Value;
+
+use crate::formatters::{self, Table};
+use crate::responses::TopLevelResponse;
+
+// Backslash-escapes the characters that Markdown could take for formatting, and the pipes
+// that would otherwise end a table cell
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '|' | '#' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' | '\r' => escaped.push(' '),
+            _ => escaped.push(c),
+        }
+    }
+    return escaped;
+}
+
+// Strings are escaped, and null is an empty cell
+fn cell(value: &Value) -> String {
+    match value {
+        Value::String(string) => escape(string),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+// Columns of numbers (and empty cells) are aligned to the right
+fn render_table(columns: &[String], rows: &[&[Value]], lines: &mut Vec<String>) {
+    if rows.is_empty() {
+        lines.push("_None._".to_string());
+        return;
+    }
+
+    let alignments: Vec<&str> = (0..columns.len())
+        .map(|i| {
+            let numeric = rows.iter().any(|row| row[i].is_number())
+                && rows
+                    .iter()
+                    .all(|row| row[i].is_number() || row[i].is_null());
+            if numeric {
+                "---:"
+            } else {
+                "---"
+            }
+        })
+        .collect();
+    lines.push(format!("| {} |", columns.join(" | ")));
+    lines.push(format!("| {} |", alignments.join(" | ")));
+    for row in rows.iter() {
+        let cells: Vec<String> = row.iter().map(cell).collect();
+        lines.push(format!("| {} |", cells.join(" | ")));
+    }
+}
+
+// The flattened tree views are split back into a table per group (or user), under a heading
+// of its own. The first two columns name the group (or user), and a row whose third column
+// is null stands for one without memberships.
+fn render_tree(table: &Table, lines: &mut Vec<String>) {
+    let mut start = 0;
+    while start < table.rows.len() {
+        let key = &table.rows[start][..2];
+        let end = start
+            + table.rows[start..]
+                .iter()
+                .take_while(|row| &row[..2] == key)
+                .count();
+        lines.push(String::new());
+        lines.push(format!("### {} ({})", cell(&key[0]), cell(&key[1])));
+        lines.push(String::new());
+        let rows: Vec<&[Value]> = table.rows[start..end]
+            .iter()
+            .filter(|row| !row[2].is_null())
+            .map(|row| &row[2..])
+            .collect();
+        render_table(&table.columns[2..], &rows, lines);
+        start = end;
+    }
+}
+
+fn heading(response: &TopLevelResponse) -> Option<String> {
+    let heading = match response {
+        TopLevelResponse::GroupOverview(_) => "Groups".to_string(),
+        TopLevelResponse::UserOverview(_) => "Users".to_string(),
+        TopLevelResponse::GroupTree(_) => "Users by group".to_string(),
+        TopLevelResponse::UserTree(_) => "Groups by user".to_string(),
+        TopLevelResponse::UserQuery(result) => {
+            format!("Groups of user {}", escape(&result.user.user_name))
+        }
+        TopLevelResponse::GroupQuery(result) => {
+            format!("Users of group {}", escape(&result.group.group_name))
+        }
+        TopLevelResponse::MembershipQuery(result) => format!(
+            "Membership of {} in {}",
+            escape(&result.user_name),
+            escape(&result.group_name)
+        ),
+        TopLevelResponse::Audit(_) => "Audit findings".to_string(),
+        TopLevelResponse::Compliance(_) => "Compliance checks".to_string(),
+        TopLevelResponse::Matrix(_) => "Membership matrix".to_string(),
+        TopLevelResponse::Report(_)
+        | TopLevelResponse::Batch(_)
+        | TopLevelResponse::NoResponse(_) => return None,
+    };
+    return Some(heading);
+}
+
+// The entries of a batch follow one another, each under its own heading. Errors, including
+// those of failed entries, are left to the caller.
+fn render(response: &TopLevelResponse, lines: &mut Vec<String>) {
+    if let TopLevelResponse::Batch(result) = response {
+        for entry in result.entries.iter() {
+            render(&entry.response, lines);
+        }
+        return;
+    }
+
+    let (heading, table) = match (heading(response), formatters::table(response)) {
+        (Some(heading), Some(table)) => (heading, table),
+        _ => return,
+    };
+    if !lines.is_empty() {
+        lines.push(String::new());
+    }
+    lines.push(format!("## {}", heading));
+    match response {
+        TopLevelResponse::GroupTree(_) | TopLevelResponse::UserTree(_) => {
+            render_tree(&table, lines);
+        }
+        _ => {
+            lines.push(String::new());
+            let rows: Vec<&[Value]> = table.rows.iter().map(|row| &row[..]).collect();
+            render_table(&table.columns, &rows, lines);
+        }
+    }
+}
+
+// None for responses with nothing to render, i.e. errors
+pub fn markdown(response: &TopLevelResponse) -> Option<String> {
+    let mut lines: Vec<String> = vec![];
+    render(response, &mut lines);
+    if lines.is_empty() {
+        return None;
+    }
+    return Some(lines.join("\n"));
+}
@@ -0,0 +1,238 @@
+// Contains logic for enumerating users and groups through the C library's
+// Name Service Switch, so that whatever /etc/nsswitch.conf configures is used
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+
+use libc::{c_char, c_int, group};
+
+use crate::errors::{io_error, Error, Input};
+use crate::records::{GroupEntry, PasswdEntry, Provenance};
+
+// The element type of the list filled in by getgrouplist(3) differs between platforms
+#[cfg(target_os = "macos")]
+type GroupListId = c_int;
+#[cfg(not(target_os = "macos"))]
+type GroupListId = libc::gid_t;
+
+const MAX_GROUP_LIST_SIZE: c_int = 65536;
+
+// Group lists are read while reading groups, so failures are reported against those
+fn group_list_error(message: String) -> Error {
+    return io_error(
+        Input::Groups,
+        "nss:group".to_string(),
+        IoError::new(IoErrorKind::InvalidData, message),
+    );
+}
+
+unsafe fn string_from_c(ptr: *const c_char) -> String {
+    if ptr.is_null() {
+        return String::new();
+    }
+    return CStr::from_ptr(ptr).to_string_lossy().into_owned();
+}
+
+unsafe fn group_entry_from_c(entry: &group) -> GroupEntry {
+    let mut usernames = vec![];
+    let mut member = entry.gr_mem;
+    while !member.is_null() && !(*member).is_null() {
+        usernames.push(string_from_c(*member));
+        member = member.offset(1);
+    }
+
+    GroupEntry {
+        group: string_from_c(entry.gr_name),
+        group_id: entry.gr_gid as i64,
+        usernames: usernames,
+        administrators: vec![],
+        provenance: Provenance {
+            file: "nss:group".to_string(),
+            line: None,
+        },
+        member_provenance: vec![],
+    }
+}
+
+pub fn read_users() -> Result<Vec<PasswdEntry>, Error> {
+    let mut users = vec![];
+
+    unsafe {
+        libc::setpwent();
+        loop {
+            let entry = libc::getpwent();
+            if entry.is_null() {
+                break;
+            }
+            users.push(PasswdEntry {
+                user: string_from_c((*entry).pw_name),
+                user_id: (*entry).pw_uid as i64,
+                primary_group_id: (*entry).pw_gid as i64,
+                gecos: string_from_c((*entry).pw_gecos),
+                home_directory: string_from_c((*entry).pw_dir),
+                shell: string_from_c((*entry).pw_shell),
+                provenance: Provenance {
+                    file: "nss:passwd".to_string(),
+                    line: None,
+                },
+            });
+        }
+        libc::endpwent();
+    }
+
+    return Ok(users);
+}
+
+// Asks NSS for every group that `user_name` belongs to, including groups that
+// a backend may decline to enumerate through getgrent(3)
+fn read_group_list(user_name: &str, primary_group_id: i64) -> Result<Vec<i64>, Error> {
+    let c_user_name = CString::new(user_name)
+        .map_err(|_| group_list_error(format!("user name {} contains a NUL byte", user_name)))?;
+
+    let mut list_size: c_int = 32;
+    loop {
+        let mut group_ids: Vec<GroupListId> = vec![0; list_size as usize];
+        let mut found_count = list_size;
+        let result = unsafe {
+            libc::getgrouplist(
+                c_user_name.as_ptr(),
+                primary_group_id as _,
+                group_ids.as_mut_ptr(),
+                &mut found_count,
+            )
+        };
+
+        if result >= 0 {
+            group_ids.truncate(found_count as usize);
+            return Ok(group_ids.iter().map(|id| *id as i64).collect());
+        }
+
+        // Some platforms report the required size, others leave it to us to guess
+        list_size = if found_count > list_size {
+            found_count
+        } else {
+            list_size * 2
+        };
+        if list_size > MAX_GROUP_LIST_SIZE {
+            return Err(group_list_error(format!(
+                "group list for {} is too large",
+                user_name
+            )));
+        }
+    }
+}
+
+pub fn read_groups() -> Result<Vec<GroupEntry>, Error> {
+    let mut groups = vec![];
+
+    unsafe {
+        libc::setgrent();
+        loop {
+            let entry = libc::getgrent();
+            if entry.is_null() {
+                break;
+            }
+            groups.push(group_entry_from_c(&*entry));
+        }
+        libc::endgrent();
+    }
+
+    let mut group_positions: HashMap<i64, usize> = groups
+        .iter()
+        .enumerate()
+        .map(|(i, group)| (group.group_id, i))
+        .collect();
+
+    for user in read_users()? {
+        for group_id in read_group_list(&user.user, user.primary_group_id)? {
+            if group_id == user.primary_group_id {
+                continue;
+            }
+
+            if let Entry::Vacant(position) = group_positions.entry(group_id) {
+                let entry = unsafe { libc::getgrgid(group_id as _) };
+                if entry.is_null() {
+                    continue;
+                }
+                groups.push(unsafe { group_entry_from_c(&*entry) });
+                position.insert(groups.len() - 1);
+            }
+
+            let group = &mut groups[group_positions[&group_id]];
+            if !group.usernames.contains(&user.user) {
+                group.usernames.push(user.user.clone());
+            }
+        }
+    }
+
+    return Ok(groups);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::load;
+
+    // Whether /etc/nsswitch.conf lists the `files` source for both the passwd and group
+    // databases. Hosts without one (e.g. sssd-only, or musl without NSS) are not checked.
+    fn uses_files_source() -> bool {
+        let conf = match std::fs::read_to_string("/etc/nsswitch.conf") {
+            Ok(conf) => conf,
+            Err(_) => return false,
+        };
+        return ["passwd:", "group:"].iter().all(|database| {
+            conf.lines().any(|line| {
+                line.trim()
+                    .strip_prefix(database)
+                    .is_some_and(|sources| sources.split_whitespace().any(|s| s == "files"))
+            })
+        });
+    }
+
+    // With the `files` backend in /etc/nsswitch.conf, every user and group in the flat files
+    // is also enumerated through NSS. Other backends may add more, so only inclusion is checked.
+    #[test]
+    fn reads_the_flat_files_through_nss() {
+        if !uses_files_source() {
+            eprintln!("Skipping: /etc/nsswitch.conf does not use the `files` source");
+            return;
+        }
+
+        let mut skipped_lines = vec![];
+        let file_users = load::read_users(Path::new("/etc/passwd"), &mut skipped_lines).unwrap();
+        let file_groups = load::read_groups(Path::new("/etc/group"), &mut skipped_lines).unwrap();
+        let nss_users = read_users().unwrap();
+        let nss_groups = read_groups().unwrap();
+
+        for file_user in file_users.iter() {
+            let nss_user = nss_users
+                .iter()
+                .find(|user| user.user == file_user.user)
+                .unwrap_or_else(|| panic!("user {} not read through NSS", file_user.user));
+            assert_eq!(nss_user.user_id, file_user.user_id);
+            assert_eq!(nss_user.primary_group_id, file_user.primary_group_id);
+            assert_eq!(nss_user.home_directory, file_user.home_directory);
+            assert_eq!(nss_user.shell, file_user.shell);
+        }
+
+        for file_group in file_groups.iter() {
+            let nss_group = nss_groups
+                .iter()
+                .find(|group| group.group == file_group.group)
+                .unwrap_or_else(|| panic!("group {} not read through NSS", file_group.group));
+            assert_eq!(nss_group.group_id, file_group.group_id);
+            for username in file_group.usernames.iter() {
+                assert!(
+                    nss_group.usernames.contains(username),
+                    "{} not listed as a member of {} through NSS",
+                    username,
+                    file_group.group
+                );
+            }
+        }
+    }
+}
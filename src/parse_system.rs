@@ -1,61 +1,162 @@
-// Contains logic for parsing system files such as /etc/passwd and /etc/group
+// Contains logic for parsing system files such as /etc/passwd, /etc/group and /etc/gshadow
 
 pub use crate::errors::{invalid_system_state, missing_field_error, Error};
-pub use crate::records::{GroupEntry, PasswdEntry};
+pub use crate::records::{GShadowEntry, GroupEntry, PasswdEntry, Provenance};
 
-pub fn remove_comment_from_line<'a>(possibly_commented_line: &'a str) -> &str {
-    let mut line_split_iter = (*possibly_commented_line).splitn(2, "#").into_iter();
-    return line_split_iter.next().expect("Logic error");
+// Only whole lines are comments, as a `#` may appear within a field such as the GECOS field
+pub fn is_comment_line(line: &str) -> bool {
+    return line.trim_start().starts_with('#');
 }
 
-pub fn parse_passwd_line<'a, 'b>(unparsed_line: &'a str) -> Result<PasswdEntry, Box<dyn Error>> {
+// Whether a line is a legacy NIS compatibility entry (e.g. `+::::::` or `+@netgroup`),
+// which makes the C library splice in records from NIS
+pub fn is_legacy_entry(line: &str) -> bool {
+    return line.starts_with('+');
+}
+
+pub fn parse_passwd_line(
+    unparsed_line: &str,
+    provenance: Provenance,
+) -> Result<PasswdEntry, Error> {
     let mut split_line = unparsed_line.split(":");
 
-    let username = split_line.next().ok_or(missing_field_error("username"))?;
-    let _ = split_line.next(); // skip description
-    let userid_raw = split_line.next().ok_or(missing_field_error("user ID"))?;
-    let groupid_raw = split_line.next().ok_or(missing_field_error("group ID"))?;
+    let username = split_line
+        .next()
+        .ok_or(missing_field_error("username", &provenance))?;
+    let _ = split_line.next(); // skip password
+    let userid_raw = split_line
+        .next()
+        .ok_or(missing_field_error("user ID", &provenance))?;
+    let groupid_raw = split_line
+        .next()
+        .ok_or(missing_field_error("group ID", &provenance))?;
+    let gecos = split_line
+        .next()
+        .ok_or(missing_field_error("GECOS", &provenance))?;
+    let home_directory = split_line
+        .next()
+        .ok_or(missing_field_error("home directory", &provenance))?;
+    let shell = split_line
+        .next()
+        .ok_or(missing_field_error("shell", &provenance))?;
 
     let userid = String::from(userid_raw);
     let userid_parsed = userid
         .parse::<i64>()
-        .map_err(|_| invalid_system_state("user ID number"))?;
+        .map_err(|_| invalid_system_state("user ID number", &provenance))?;
 
     let groupid = String::from(groupid_raw);
     let groupid_parsed = groupid
         .parse::<i64>()
-        .map_err(|_| invalid_system_state("group ID number"))?;
+        .map_err(|_| invalid_system_state("group ID number", &provenance))?;
 
     Ok(PasswdEntry {
         user: String::from(username.trim()),
         user_id: userid_parsed,
         primary_group_id: groupid_parsed,
+        gecos: String::from(gecos),
+        home_directory: String::from(home_directory),
+        shell: String::from(shell.trim()),
+        provenance: provenance,
     })
 }
 
-pub fn parse_group_line<'a, 'b>(unparsed_line: &'a str) -> Result<GroupEntry, Box<dyn Error>> {
+pub fn parse_group_line(unparsed_line: &str, provenance: Provenance) -> Result<GroupEntry, Error> {
     let mut split_line = unparsed_line.split(":");
 
-    let groupname = split_line.next().ok_or(missing_field_error("group name"))?;
+    let groupname = split_line
+        .next()
+        .ok_or(missing_field_error("group name", &provenance))?;
     let _ = split_line.next(); // skip password
-    let groupid_raw = split_line.next().ok_or(missing_field_error("group ID"))?;
-    let usernames_raw = split_line.next().ok_or(missing_field_error("usernames"))?;
+    let groupid_raw = split_line
+        .next()
+        .ok_or(missing_field_error("group ID", &provenance))?;
+    let usernames_raw = split_line
+        .next()
+        .ok_or(missing_field_error("usernames", &provenance))?;
 
     let groupid = String::from(groupid_raw);
     let groupid_parsed = groupid
         .parse::<i64>()
-        .map_err(|_| invalid_system_state("group ID number"))?;
+        .map_err(|_| invalid_system_state("group ID number", &provenance))?;
+
+    Ok(GroupEntry {
+        group: String::from(groupname),
+        group_id: groupid_parsed,
+        usernames: parse_username_list(usernames_raw),
+        administrators: vec![],
+        provenance: provenance,
+        member_provenance: vec![],
+    })
+}
+
+pub fn parse_gshadow_line(
+    unparsed_line: &str,
+    provenance: Provenance,
+) -> Result<GShadowEntry, Error> {
+    let mut split_line = unparsed_line.split(":");
+
+    let groupname = split_line
+        .next()
+        .ok_or(missing_field_error("group name", &provenance))?;
+    let _ = split_line.next(); // skip password
+    let administrators_raw = split_line
+        .next()
+        .ok_or(missing_field_error("administrators", &provenance))?;
+    let usernames_raw = split_line
+        .next()
+        .ok_or(missing_field_error("usernames", &provenance))?;
 
-    let usernames = usernames_raw
+    Ok(GShadowEntry {
+        group: String::from(groupname),
+        administrators: parse_username_list(administrators_raw),
+        usernames: parse_username_list(usernames_raw),
+        provenance: provenance,
+    })
+}
+
+fn parse_username_list(usernames_raw: &str) -> Vec<String> {
+    return usernames_raw
         .split(",")
         .map(|field| field.trim())
         .filter(|field| !field.is_empty())
         .map(String::from)
         .collect();
+}
 
-    Ok(GroupEntry {
-        group: String::from(groupname),
-        group_id: groupid_parsed,
-        usernames: usernames,
-    })
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provenance() -> Provenance {
+        return Provenance {
+            file: "/etc/passwd".to_string(),
+            line: Some(1),
+        };
+    }
+
+    #[test]
+    fn treats_only_whole_lines_as_comments() {
+        assert!(is_comment_line(
+            "# alice:x:1000:1000::/home/alice:/bin/bash"
+        ));
+        assert!(is_comment_line("   #"));
+        assert!(!is_comment_line(
+            "alice:x:1000:1000:Alice,Room #3,,:/home/alice:/bin/bash"
+        ));
+    }
+
+    #[test]
+    fn parses_a_hash_in_the_gecos_field() {
+        let line = "alice:x:1000:1000:Alice,Room #3,,:/home/alice:/bin/bash";
+        assert!(!is_comment_line(line));
+        let entry = match parse_passwd_line(line, provenance()) {
+            Ok(entry) => entry,
+            Err(error) => panic!("rejected `{}`: {}", line, error),
+        };
+        assert_eq!(entry.user, "alice");
+        assert_eq!(entry.gecos, "Alice,Room #3,,");
+        assert_eq!(entry.home_directory, "/home/alice");
+        assert_eq!(entry.shell, "/bin/bash");
+    }
 }
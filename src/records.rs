@@ -1,9 +1,45 @@
+// Where a record was read from. The line number is 1-based, and is None for
+// sources that are not line-oriented (such as NSS or userdb JSON records).
+#[derive(Clone, Hash)]
+pub struct Provenance {
+    pub file: String,
+    pub line: Option<usize>,
+}
+
+// A line (or userdb record) that could not be parsed, and was skipped
+#[derive(Clone)]
+pub struct SkippedLine {
+    pub provenance: Provenance,
+    pub text: String,
+    pub reason: String,
+}
+
 // Entry from /etc/passwd representing a user
 #[derive(Clone, Hash)]
 pub struct PasswdEntry {
     pub user: String,
     pub user_id: i64,
     pub primary_group_id: i64,
+    pub gecos: String,
+    pub home_directory: String,
+    pub shell: String,
+    pub provenance: Provenance,
+}
+
+// Shells that are used to deny a user interactive logins
+const NO_LOGIN_SHELLS: [&'static str; 2] = ["nologin", "false"];
+
+impl PasswdEntry {
+    // The user's full name, i.e. the first comma-separated GECOS subfield
+    pub fn full_name(&self) -> String {
+        return self.gecos.split(',').next().unwrap_or("").to_string();
+    }
+
+    // Whether the user can log in, judged by their shell. An empty shell means /bin/sh.
+    pub fn has_login_shell(&self) -> bool {
+        let shell_name = self.shell.rsplit('/').next().unwrap_or("");
+        return !NO_LOGIN_SHELLS.contains(&shell_name);
+    }
 }
 
 // Entry from /etc/group representing a group
@@ -12,4 +48,30 @@ pub struct GroupEntry {
     pub group: String,
     pub group_id: i64,
     pub usernames: Vec<String>,
+    pub administrators: Vec<String>,
+    pub provenance: Provenance,
+    // Where members were listed, for those not listed by the group's own record
+    // (e.g. members merged in from /etc/gshadow)
+    pub member_provenance: Vec<(String, Provenance)>,
+}
+
+impl GroupEntry {
+    // Where `username` was listed as a member of this group
+    pub fn member_provenance(&self, username: &str) -> &Provenance {
+        return self
+            .member_provenance
+            .iter()
+            .find(|(member, _)| member == username)
+            .map(|(_, provenance)| provenance)
+            .unwrap_or(&self.provenance);
+    }
+}
+
+// Entry from /etc/gshadow holding the administrators and members of a group
+#[derive(Clone, Hash)]
+pub struct GShadowEntry {
+    pub group: String,
+    pub administrators: Vec<String>,
+    pub usernames: Vec<String>,
+    pub provenance: Provenance,
 }
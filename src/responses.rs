@@ -5,28 +5,220 @@ use std::string::String;
 use serde_derive::Serialize;
 use serde_json::ser;
 
+use crate::error_codes;
+use crate::errors::{usage_error, Error};
+use crate::formatters;
+use crate::graph::Graph;
+use crate::records;
+
+// Whether a user belongs to a group by their primary group ID, by being listed as a member, or both
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Membership {
+    Primary,
+    Supplementary,
+    Both,
+}
+
+impl Membership {
+    // The marker appended to names in text output; plain supplementary memberships are unmarked
+    fn text_marker(&self) -> &'static str {
+        match self {
+            Membership::Primary => " (primary)",
+            Membership::Supplementary => "",
+            Membership::Both => " (both)",
+        }
+    }
+}
+
+// A line of a source file; the line is left out for sources that are not line-oriented
+#[derive(Serialize, Clone)]
+pub struct Location {
+    pub file: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+}
+
+impl Location {
+    pub fn of(provenance: &records::Provenance) -> Location {
+        return Location {
+            file: provenance.file.clone(),
+            line: provenance.line,
+        };
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}", self.file, line),
+            None => write!(f, "{}", self.file),
+        }
+    }
+}
+
+// The entries that produced a membership: the passwd entry giving the user their
+// primary group, and the group (or gshadow, or userdb) entry listing them as a member
+#[derive(Serialize, Clone)]
+pub struct Provenance {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<Location>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<Location>,
+}
+
+impl Display for Provenance {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let locations: Vec<String> = self
+            .user
+            .iter()
+            .chain(self.group.iter())
+            .map(|location| location.to_string())
+            .collect();
+        write!(f, "via {}", locations.join(", "))
+    }
+}
+
+// A line of a source file that could not be parsed, and was left out of the results
+#[derive(Serialize, Clone)]
+pub struct Warning {
+    pub file: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    pub text: String,
+    pub reason: String,
+}
+
+impl Warning {
+    pub fn of(skipped_line: &records::SkippedLine) -> Warning {
+        return Warning {
+            file: skipped_line.provenance.file.clone(),
+            line: skipped_line.provenance.line,
+            text: skipped_line.text.clone(),
+            reason: skipped_line.reason.clone(),
+        };
+    }
+
+    pub fn location(&self) -> Location {
+        return Location {
+            file: self.file.clone(),
+            line: self.line,
+        };
+    }
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}; skipping `{}`",
+            self.location(),
+            self.reason,
+            self.text
+        )
+    }
+}
+
 #[derive(Serialize, Clone)]
 pub struct Group {
     pub name: String,
     pub id: i64,
+    // Only present when listing the groups of a user
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub membership: Option<Membership>,
+    // Only present with --explain
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+    // Only present in the group listing with -l
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub members: Option<Vec<String>>,
+}
+
+impl Display for Group {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.membership {
+            Some(membership) => write!(f, "{}{}", self.name, membership.text_marker())?,
+            None => write!(f, "{}", self.name)?,
+        }
+        match &self.provenance {
+            Some(provenance) => write!(f, "  {}", provenance),
+            None => write!(f, ""),
+        }
+    }
+}
+
+// How a user relates to a group. Administrators (from /etc/gshadow) take
+// precedence, followed by users whose primary group it is.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Member,
+    Administrator,
+    Primary,
 }
 
 #[derive(Serialize, Clone)]
 pub struct User {
     pub name: String,
     pub id: i64,
+    pub role: Role,
+    pub membership: Membership,
+    pub full_name: String,
+    pub home_directory: String,
+    pub shell: String,
+    // Only present with --explain
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+}
+
+impl Display for User {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.name, self.membership.text_marker())?;
+        match &self.provenance {
+            Some(provenance) => write!(f, "  {}", provenance),
+            None => write!(f, ""),
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct UserSummary {
+    pub name: String,
+    pub id: i64,
+    pub primary_group_id: i64,
+    // None if no group carries the user's primary group ID
+    pub primary_group: Option<String>,
+    pub supplementary_group_count: usize,
+    pub shell: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct GroupTreeNode {
+    pub name: String,
+    pub id: i64,
+    pub users: Vec<User>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct UserTreeNode {
+    pub name: String,
+    pub id: i64,
+    pub groups: Vec<Group>,
 }
 
 #[derive(Serialize, Clone)]
 pub struct UserQueryResponse {
     pub user_name: String,
+    pub user_id: i64,
     pub groups: Vec<Group>,
 }
 
 #[derive(Serialize, Clone)]
 pub struct GroupQueryResponse {
     pub group_name: String,
+    pub group_id: i64,
     pub users: Vec<User>,
+    pub administrators: Vec<String>,
 }
 
 // Responses
@@ -35,6 +227,8 @@ pub struct GroupQueryResponse {
 #[serde(rename_all = "camelCase")]
 pub struct GroupOverviewQueryResult {
     pub api_version: String,
+    pub source_files: Vec<String>,
+    pub warnings: Vec<Warning>,
     pub groups: Vec<Group>,
 }
 
@@ -42,9 +236,112 @@ impl Display for GroupOverviewQueryResult {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         for (i, group) in self.groups.iter().enumerate() {
             if i != 0 {
-                write!(f, "\n")?;
+                writeln!(f)?;
+            }
+            write!(f, "{}", group.name)?;
+        }
+        write!(f, "")
+    }
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UserOverviewQueryResult {
+    pub api_version: String,
+    pub source_files: Vec<String>,
+    pub warnings: Vec<Warning>,
+    pub users: Vec<UserSummary>,
+}
+
+impl Display for UserOverviewQueryResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let rows: Vec<[String; 4]> = self
+            .users
+            .iter()
+            .map(|user| {
+                [
+                    user.name.clone(),
+                    user.id.to_string(),
+                    match &user.primary_group {
+                        Some(group) => group.clone(),
+                        None => user.primary_group_id.to_string(),
+                    },
+                    user.supplementary_group_count.to_string(),
+                ]
+            })
+            .collect();
+
+        let mut widths = [0; 4];
+        for row in rows.iter() {
+            for (column, value) in row.iter().enumerate() {
+                widths[column] = widths[column].max(value.chars().count());
+            }
+        }
+
+        for (i, row) in rows.iter().enumerate() {
+            if i != 0 {
+                writeln!(f)?;
+            }
+            write!(
+                f,
+                "{:<w0$}  {:>w1$}  {:<w2$}  {:>w3$}",
+                row[0],
+                row[1],
+                row[2],
+                row[3],
+                w0 = widths[0],
+                w1 = widths[1],
+                w2 = widths[2],
+                w3 = widths[3]
+            )?;
+        }
+        write!(f, "")
+    }
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupTreeQueryResult {
+    pub api_version: String,
+    pub source_files: Vec<String>,
+    pub warnings: Vec<Warning>,
+    pub groups: Vec<GroupTreeNode>,
+}
+
+impl Display for GroupTreeQueryResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, group) in self.groups.iter().enumerate() {
+            if i != 0 {
+                writeln!(f)?;
             }
             write!(f, "{}", group.name)?;
+            for user in group.users.iter() {
+                write!(f, "\n  {}", user)?;
+            }
+        }
+        write!(f, "")
+    }
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UserTreeQueryResult {
+    pub api_version: String,
+    pub source_files: Vec<String>,
+    pub warnings: Vec<Warning>,
+    pub users: Vec<UserTreeNode>,
+}
+
+impl Display for UserTreeQueryResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, user) in self.users.iter().enumerate() {
+            if i != 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", user.name)?;
+            for group in user.groups.iter() {
+                write!(f, "\n  {}", group)?;
+            }
         }
         write!(f, "")
     }
@@ -54,6 +351,8 @@ impl Display for GroupOverviewQueryResult {
 #[serde(rename_all = "camelCase")]
 pub struct GroupQueryResult {
     pub api_version: String,
+    pub source_files: Vec<String>,
+    pub warnings: Vec<Warning>,
     pub group: GroupQueryResponse,
 }
 
@@ -61,9 +360,9 @@ impl Display for GroupQueryResult {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         for (i, user) in self.group.users.iter().enumerate() {
             if i != 0 {
-                write!(f, "\n")?;
+                writeln!(f)?;
             }
-            write!(f, "{}", user.name)?;
+            write!(f, "{}", user)?;
         }
         write!(f, "")
     }
@@ -73,6 +372,8 @@ impl Display for GroupQueryResult {
 #[serde(rename_all = "camelCase")]
 pub struct UserQueryResult {
     pub api_version: String,
+    pub source_files: Vec<String>,
+    pub warnings: Vec<Warning>,
     pub user: UserQueryResponse,
 }
 
@@ -80,9 +381,293 @@ impl Display for UserQueryResult {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         for (i, group) in self.user.groups.iter().enumerate() {
             if i != 0 {
-                write!(f, "\n")?;
+                writeln!(f)?;
             }
-            write!(f, "{}", group.name)?;
+            write!(f, "{}", group)?;
+        }
+        write!(f, "")
+    }
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MembershipQueryResult {
+    pub api_version: String,
+    pub source_files: Vec<String>,
+    pub warnings: Vec<Warning>,
+    pub user_name: String,
+    pub group_name: String,
+    pub is_member: bool,
+    // Only present with --explain, if the user is a member
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+}
+
+impl Display for MembershipQueryResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(provenance) = &self.provenance {
+            write!(
+                f,
+                "{} is a member of {} {}",
+                self.user_name, self.group_name, provenance
+            )
+        } else if self.is_member {
+            write!(f, "{} is a member of {}", self.user_name, self.group_name)
+        } else {
+            write!(
+                f,
+                "{} is not a member of {}",
+                self.user_name, self.group_name
+            )
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Info => write!(f, "info"),
+        }
+    }
+}
+
+// A problem found by `groupls audit`. The ID names the check, and does not change between releases.
+#[derive(Serialize, Clone)]
+pub struct Finding {
+    pub id: String,
+    pub severity: Severity,
+    pub message: String,
+    pub locations: Vec<Location>,
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]: {}", self.severity, self.id, self.message)?;
+        for location in self.locations.iter() {
+            write!(f, "\n  --> {}", location)?;
+        }
+        write!(f, "")
+    }
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditQueryResult {
+    pub api_version: String,
+    pub source_files: Vec<String>,
+    pub warnings: Vec<Warning>,
+    pub findings: Vec<Finding>,
+}
+
+impl Display for AuditQueryResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, finding) in self.findings.iter().enumerate() {
+            if i != 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", finding)?;
+        }
+        write!(f, "")
+    }
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Fail,
+    // The check does not apply to the data source, e.g. file-based checks under NSS
+    Skipped,
+}
+
+impl Display for CheckStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckStatus::Pass => write!(f, "PASS"),
+            CheckStatus::Fail => write!(f, "FAIL"),
+            CheckStatus::Skipped => write!(f, "SKIP"),
+        }
+    }
+}
+
+// One check run by `groupls compliance`, with a finding per failure
+#[derive(Serialize, Clone)]
+pub struct ComplianceCheck {
+    pub id: String,
+    pub title: String,
+    pub status: CheckStatus,
+    pub remediation: String,
+    pub findings: Vec<Finding>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ComplianceQueryResult {
+    pub api_version: String,
+    pub source_files: Vec<String>,
+    pub warnings: Vec<Warning>,
+    pub passed: bool,
+    pub checks: Vec<ComplianceCheck>,
+}
+
+impl Display for ComplianceQueryResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let id_width = self
+            .checks
+            .iter()
+            .map(|check| check.id.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        for (i, check) in self.checks.iter().enumerate() {
+            if i != 0 {
+                writeln!(f)?;
+            }
+            write!(
+                f,
+                "{}  {:<w$}  {}",
+                check.status,
+                check.id,
+                check.title,
+                w = id_width
+            )?;
+            for finding in check.findings.iter() {
+                write!(f, "\n      {}", finding.message)?;
+                for location in finding.locations.iter() {
+                    write!(f, "\n        --> {}", location)?;
+                }
+            }
+            if check.status == CheckStatus::Fail {
+                write!(f, "\n      Remediation: {}", check.remediation)?;
+            }
+        }
+        write!(f, "")
+    }
+}
+
+// A user and the groups they belong to, for the per-user detail of `groupls report`
+#[derive(Serialize, Clone)]
+pub struct UserDetail {
+    pub name: String,
+    pub id: i64,
+    pub primary_group_id: i64,
+    // None if no group carries the user's primary group ID
+    pub primary_group: Option<String>,
+    pub full_name: String,
+    pub home_directory: String,
+    pub shell: String,
+    pub groups: Vec<Group>,
+}
+
+// Everything that goes into the HTML report of `groupls report`
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportQueryResult {
+    pub api_version: String,
+    pub source_files: Vec<String>,
+    pub warnings: Vec<Warning>,
+    pub groups: Vec<GroupTreeNode>,
+    pub users: Vec<UserDetail>,
+    pub findings: Vec<Finding>,
+}
+
+impl Display for ReportQueryResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} groups, {} users, {} audit findings",
+            self.groups.len(),
+            self.users.len(),
+            self.findings.len()
+        )
+    }
+}
+
+// A user heading a row of the matrix, or a group heading a column
+#[derive(Serialize, Clone)]
+pub struct MatrixAxis {
+    pub name: String,
+    pub id: i64,
+}
+
+// A marked cell of the matrix; cells without a membership are left out. The indexes point
+// into the users and groups of the matrix, as names (and even IDs) can be shared.
+#[derive(Serialize, Clone)]
+pub struct MatrixCell {
+    pub user: String,
+    pub user_index: usize,
+    pub group: String,
+    pub group_index: usize,
+    pub membership: Membership,
+}
+
+// The users by groups grid of `--matrix`, kept sparse in JSON output
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MatrixQueryResult {
+    pub api_version: String,
+    pub source_files: Vec<String>,
+    pub warnings: Vec<Warning>,
+    pub users: Vec<MatrixAxis>,
+    pub groups: Vec<MatrixAxis>,
+    pub memberships: Vec<MatrixCell>,
+}
+
+impl MatrixQueryResult {
+    // The dense grid, a row per user with a cell per group, built in one pass over the cells
+    pub fn grid(&self) -> Vec<Vec<Option<Membership>>> {
+        let mut grid = vec![vec![None; self.groups.len()]; self.users.len()];
+        for cell in self.memberships.iter() {
+            grid[cell.user_index][cell.group_index] = Some(cell.membership);
+        }
+        return grid;
+    }
+}
+
+// Cells are marked P for a primary group, S for a group listing the user, and P+S for both
+impl Display for MatrixQueryResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let user_width = self
+            .users
+            .iter()
+            .map(|user| user.name.chars().count())
+            .chain(Some("USER".len()))
+            .max()
+            .unwrap_or(0);
+        let widths: Vec<usize> = self
+            .groups
+            .iter()
+            .map(|group| group.name.chars().count().max("P+S".len()))
+            .collect();
+
+        // The last column is not padded, so that lines do not end in spaces
+        let mut header = format!("{:<width$}", "USER", width = user_width);
+        for (group, width) in self.groups.iter().zip(widths.iter()) {
+            header += &format!("  {:<width$}", group.name, width = width);
+        }
+        write!(f, "{}", header.trim_end())?;
+        for (user, cells) in self.users.iter().zip(self.grid()) {
+            let mut line = format!("{:<width$}", user.name, width = user_width);
+            for (cell, width) in cells.into_iter().zip(widths.iter()) {
+                let marker = match cell {
+                    Some(Membership::Primary) => "P",
+                    Some(Membership::Supplementary) => "S",
+                    Some(Membership::Both) => "P+S",
+                    None => "-",
+                };
+                line += &format!("  {:<width$}", marker, width = width);
+            }
+            write!(f, "\n{}", line.trim_end())?;
         }
         write!(f, "")
     }
@@ -93,57 +678,308 @@ impl Display for UserQueryResult {
 pub struct NoResponseResult {
     pub api_version: String,
     pub exit_code: i32,
+    // A machine-readable name for the kind of error, e.g. `notFound`
+    pub kind: String,
     pub error: String,
+    // The OBJECT that the error concerns, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub object: Option<String>,
+    pub warnings: Vec<Warning>,
+}
+
+impl NoResponseResult {
+    pub fn of(api_version: &str, error: &Error, warnings: Vec<Warning>) -> NoResponseResult {
+        return NoResponseResult {
+            api_version: api_version.to_string(),
+            exit_code: error.exit_code(),
+            kind: error.kind().to_string(),
+            error: error.to_string(),
+            object: match error {
+                Error::NotFound { object, .. } => Some(object.clone()),
+                _ => None,
+            },
+            warnings: warnings,
+        };
+    }
+}
+
+// One result of a query for several objects
+#[derive(Clone)]
+pub struct BatchEntry {
+    pub object: String,
+    pub response: TopLevelResponse,
+}
+
+#[derive(Clone)]
+pub struct BatchResult {
+    pub entries: Vec<BatchEntry>,
 }
 
 #[derive(Clone)]
 pub enum TopLevelResponse {
     GroupOverview(GroupOverviewQueryResult),
+    UserOverview(UserOverviewQueryResult),
+    GroupTree(GroupTreeQueryResult),
+    UserTree(UserTreeQueryResult),
     GroupQuery(GroupQueryResult),
     UserQuery(UserQueryResult),
+    MembershipQuery(MembershipQueryResult),
+    Audit(AuditQueryResult),
+    Compliance(ComplianceQueryResult),
+    Report(ReportQueryResult),
+    Matrix(MatrixQueryResult),
+    Batch(BatchResult),
     NoResponse(NoResponseResult),
 }
 
-pub fn output_response(response: TopLevelResponse, is_json: bool) {
-    let exit_code = match response.clone() {
-        TopLevelResponse::NoResponse(result) => result.exit_code,
-        _ => 0,
-    };
+impl TopLevelResponse {
+    // A batch exits with the code of its first failed entry, an audit fails on any error,
+    // and a compliance report fails on any failed check
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            TopLevelResponse::NoResponse(result) => result.exit_code,
+            TopLevelResponse::MembershipQuery(result) if !result.is_member => {
+                error_codes::NOT_A_MEMBER
+            }
+            TopLevelResponse::Audit(result)
+                if result
+                    .findings
+                    .iter()
+                    .any(|finding| finding.severity == Severity::Error) =>
+            {
+                error_codes::AUDIT_ERRORS_FOUND
+            }
+            TopLevelResponse::Compliance(result) if !result.passed => {
+                error_codes::COMPLIANCE_CHECKS_FAILED
+            }
+            TopLevelResponse::Batch(result) => result
+                .entries
+                .iter()
+                .map(|entry| entry.response.exit_code())
+                .find(|exit_code| *exit_code != 0)
+                .unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    // The entries of a batch all carry the warnings of the one load of the source files
+    pub fn warnings(&self) -> &[Warning] {
+        match self {
+            TopLevelResponse::GroupOverview(result) => &result.warnings,
+            TopLevelResponse::UserOverview(result) => &result.warnings,
+            TopLevelResponse::GroupTree(result) => &result.warnings,
+            TopLevelResponse::UserTree(result) => &result.warnings,
+            TopLevelResponse::GroupQuery(result) => &result.warnings,
+            TopLevelResponse::UserQuery(result) => &result.warnings,
+            TopLevelResponse::MembershipQuery(result) => &result.warnings,
+            TopLevelResponse::Audit(result) => &result.warnings,
+            TopLevelResponse::Compliance(result) => &result.warnings,
+            TopLevelResponse::Report(result) => &result.warnings,
+            TopLevelResponse::Matrix(result) => &result.warnings,
+            TopLevelResponse::Batch(result) => match result.entries.first() {
+                Some(entry) => entry.response.warnings(),
+                None => &[],
+            },
+            TopLevelResponse::NoResponse(result) => &result.warnings,
+        }
+    }
+}
+
+// How results are printed
+#[derive(Clone, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    // A SARIF log of skipped lines and findings, for static analysis tooling
+    Sarif,
+    // One record per row, with a header row
+    Csv,
+    Tsv,
+    // One JSON record per line
+    Ndjson,
+    // The graph of users and groups, as Graphviz DOT or as a Mermaid flowchart
+    Dot,
+    Mermaid,
+    // Tables under headings, for pull requests and wiki pages
+    Markdown,
+    // One record per line, as laid out by --template
+    Template(formatters::Template),
+    // An aligned table of the group or user listing, for -l
+    Long(formatters::LongListing),
+    // A self-contained HTML report, written to the given file (or to stdout for `-`)
+    Html(String),
+}
+
+impl OutputFormat {
+    pub const NAMES: [&'static str; 9] = [
+        "text", "json", "sarif", "csv", "tsv", "ndjson", "dot", "mermaid", "markdown",
+    ];
 
+    pub fn from_name(name: &str) -> Option<OutputFormat> {
+        match name {
+            "text" => Some(OutputFormat::Text),
+            "json" => Some(OutputFormat::Json),
+            "sarif" => Some(OutputFormat::Sarif),
+            "csv" => Some(OutputFormat::Csv),
+            "tsv" => Some(OutputFormat::Tsv),
+            "ndjson" => Some(OutputFormat::Ndjson),
+            "dot" => Some(OutputFormat::Dot),
+            "mermaid" => Some(OutputFormat::Mermaid),
+            "markdown" => Some(OutputFormat::Markdown),
+            _ => None,
+        }
+    }
+
+    // Whether errors are printed to stdout as JSON, rather than to stderr
+    pub fn reports_errors_as_json(&self) -> bool {
+        match self {
+            OutputFormat::Json | OutputFormat::Sarif | OutputFormat::Ndjson => true,
+            OutputFormat::Text
+            | OutputFormat::Csv
+            | OutputFormat::Tsv
+            | OutputFormat::Dot
+            | OutputFormat::Mermaid
+            | OutputFormat::Markdown
+            | OutputFormat::Template(_)
+            | OutputFormat::Long(_)
+            | OutputFormat::Html(_) => false,
+        }
+    }
+
+    // Fails if the format asks for fields or columns that `response` does not have
+    pub fn check(&self, response: &TopLevelResponse) -> Result<(), Error> {
+        match self {
+            OutputFormat::Template(template) => template.check(response),
+            OutputFormat::Long(listing) => listing.check(response),
+            OutputFormat::Dot | OutputFormat::Mermaid => match response {
+                TopLevelResponse::NoResponse(_) => Ok(()),
+                _ if Graph::of(response).is_some() => Ok(()),
+                _ => Err(usage_error(
+                    "The dot and mermaid formats only draw the group and user listings and -u and -g queries"
+                        .to_string(),
+                )),
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+fn print_json<T: serde::Serialize>(result: &T) {
+    let json = ser::to_string(result).expect("Could not stringify JSON");
+    println!("{}", json);
+}
+
+// Batches are printed as one JSON document per line, or as text sections headed by each object.
+// A failed entry does not stop the batch, so its text is an `Error:` rather than a `Fatal:`.
+pub fn print_response(response: &TopLevelResponse, is_json: bool) {
     match response {
+        TopLevelResponse::Batch(result) => {
+            for (i, entry) in result.entries.iter().enumerate() {
+                if !is_json {
+                    if i != 0 {
+                        println!();
+                    }
+                    println!("{}:", entry.object);
+                }
+                match &entry.response {
+                    TopLevelResponse::NoResponse(result) if !is_json => {
+                        eprintln!("Error: {}", result.error);
+                    }
+                    other => print_response(other, is_json),
+                }
+            }
+        }
         TopLevelResponse::NoResponse(result) => {
             if is_json {
-                let json = ser::to_string(&result).expect("Could not stringify JSON");
-                println!("{}", json);
+                print_json(result);
             } else {
                 eprintln!("Fatal: {}", result.error);
             }
         }
         TopLevelResponse::GroupOverview(result) => {
             if is_json {
-                let json = ser::to_string(&result).expect("Could not stringify JSON");
-                println!("{}", json);
+                print_json(result);
+            } else {
+                println!("{}", result);
+            }
+        }
+        TopLevelResponse::UserOverview(result) => {
+            if is_json {
+                print_json(result);
+            } else {
+                println!("{}", result);
+            }
+        }
+        TopLevelResponse::GroupTree(result) => {
+            if is_json {
+                print_json(result);
+            } else {
+                println!("{}", result);
+            }
+        }
+        TopLevelResponse::UserTree(result) => {
+            if is_json {
+                print_json(result);
             } else {
                 println!("{}", result);
             }
         }
         TopLevelResponse::UserQuery(result) => {
             if is_json {
-                let json = ser::to_string(&result).expect("Could not stringify JSON");
-                println!("{}", json);
+                print_json(result);
+            } else {
+                println!("{}", result);
+            }
+        }
+        TopLevelResponse::MembershipQuery(result) => {
+            if is_json {
+                print_json(result);
             } else {
                 println!("{}", result);
             }
         }
         TopLevelResponse::GroupQuery(result) => {
             if is_json {
-                let json = ser::to_string(&result).expect("Could not stringify JSON");
-                println!("{}", json);
+                print_json(result);
+            } else {
+                println!("{}", result);
+            }
+        }
+        TopLevelResponse::Audit(result) => {
+            if is_json {
+                print_json(result);
+            } else {
+                println!("{}", result);
+            }
+        }
+        TopLevelResponse::Compliance(result) => {
+            if is_json {
+                print_json(result);
+            } else {
+                println!("{}", result);
+            }
+        }
+        TopLevelResponse::Report(result) => {
+            if is_json {
+                print_json(result);
+            } else {
+                println!("{}", result);
+            }
+        }
+        TopLevelResponse::Matrix(result) => {
+            if is_json {
+                print_json(result);
             } else {
                 println!("{}", result);
             }
         }
     };
+}
 
-    exit(exit_code);
+pub fn output_response(response: TopLevelResponse, format: OutputFormat) -> ! {
+    if let Err(error) = formatters::formatter(&format).print(&response) {
+        eprintln!("Fatal: {}", error);
+        exit(error.exit_code());
+    }
+    exit(response.exit_code());
 }
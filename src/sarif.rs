@@ -0,0 +1,242 @@
+// Contains the SARIF 2.1.0 rendering of groupls results, for static analysis tooling.
+// Only the parts of the format that groupls has something to say about are modelled.
+
+use serde_derive::Serialize;
+
+use crate::responses::{Finding, Location, Severity, TopLevelResponse};
+
+const SARIF_VERSION: &'static str = "2.1.0";
+const SARIF_SCHEMA: &'static str = "https://json.schemastore.org/sarif-2.1.0.json";
+const INFORMATION_URI: &'static str = "https://github.com/philip-peterson/groupls";
+
+// The rule reported for lines of the passwd, group and gshadow files that could not be parsed
+const UNPARSEABLE_LINE_RULE: &'static str = "unparseable-line";
+
+#[derive(Serialize)]
+struct Message {
+    text: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReportingDescriptor {
+    id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    short_description: Option<Message>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    help: Option<Message>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ToolComponent {
+    name: String,
+    version: String,
+    information_uri: String,
+    rules: Vec<ReportingDescriptor>,
+}
+
+#[derive(Serialize)]
+struct Tool {
+    driver: ToolComponent,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Notification {
+    level: String,
+    message: Message,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Invocation {
+    execution_successful: bool,
+    exit_code: i32,
+    tool_execution_notifications: Vec<Notification>,
+}
+
+#[derive(Serialize)]
+struct ArtifactLocation {
+    uri: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Region {
+    start_line: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PhysicalLocation {
+    artifact_location: ArtifactLocation,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    region: Option<Region>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ResultLocation {
+    physical_location: PhysicalLocation,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult {
+    rule_id: String,
+    level: String,
+    message: Message,
+    locations: Vec<ResultLocation>,
+}
+
+#[derive(Serialize)]
+struct Run {
+    tool: Tool,
+    invocations: Vec<Invocation>,
+    results: Vec<SarifResult>,
+}
+
+#[derive(Serialize)]
+pub struct SarifLog {
+    version: String,
+    #[serde(rename = "$schema")]
+    schema: String,
+    runs: Vec<Run>,
+}
+
+fn message(text: String) -> Message {
+    return Message { text: text };
+}
+
+// Absolute paths become `file://` URIs; anything else (such as a relative path) is left
+// as a relative reference
+fn artifact_uri(file: &str) -> String {
+    let escaped = file.replace('%', "%25").replace(' ', "%20");
+    if escaped.starts_with('/') {
+        return format!("file://{}", escaped);
+    }
+    return escaped;
+}
+
+fn result_location(location: &Location) -> ResultLocation {
+    return ResultLocation {
+        physical_location: PhysicalLocation {
+            artifact_location: ArtifactLocation {
+                uri: artifact_uri(&location.file),
+            },
+            region: location.line.map(|line| Region { start_line: line }),
+        },
+    };
+}
+
+fn level(severity: Severity) -> String {
+    match severity {
+        Severity::Error => "error".to_string(),
+        Severity::Warning => "warning".to_string(),
+        Severity::Info => "note".to_string(),
+    }
+}
+
+fn finding_result(finding: &Finding) -> SarifResult {
+    return SarifResult {
+        rule_id: finding.id.clone(),
+        level: level(finding.severity),
+        message: message(finding.message.clone()),
+        locations: finding.locations.iter().map(result_location).collect(),
+    };
+}
+
+// Adds `rule` unless a rule with the same ID was already added
+fn add_rule(rules: &mut Vec<ReportingDescriptor>, rule: ReportingDescriptor) {
+    if !rules.iter().any(|existing| existing.id == rule.id) {
+        rules.push(rule);
+    }
+}
+
+// Reports the warnings and any audit or compliance findings of `response`.
+// Errors that stopped groupls from producing a response are reported as notifications.
+pub fn sarif_log(response: &TopLevelResponse) -> SarifLog {
+    let mut rules: Vec<ReportingDescriptor> = vec![];
+    let mut results: Vec<SarifResult> = vec![];
+    let mut notifications: Vec<Notification> = vec![];
+
+    for warning in response.warnings() {
+        add_rule(
+            &mut rules,
+            ReportingDescriptor {
+                id: UNPARSEABLE_LINE_RULE.to_string(),
+                short_description: Some(message("Line could not be parsed".to_string())),
+                help: None,
+            },
+        );
+        results.push(SarifResult {
+            rule_id: UNPARSEABLE_LINE_RULE.to_string(),
+            level: level(Severity::Warning),
+            message: message(format!(
+                "{}; the line was skipped: {}",
+                warning.reason, warning.text
+            )),
+            locations: vec![result_location(&warning.location())],
+        });
+    }
+
+    match response {
+        TopLevelResponse::Audit(result) => {
+            for finding in result.findings.iter() {
+                add_rule(
+                    &mut rules,
+                    ReportingDescriptor {
+                        id: finding.id.clone(),
+                        short_description: None,
+                        help: None,
+                    },
+                );
+                results.push(finding_result(finding));
+            }
+        }
+        TopLevelResponse::Compliance(result) => {
+            for check in result.checks.iter() {
+                add_rule(
+                    &mut rules,
+                    ReportingDescriptor {
+                        id: check.id.clone(),
+                        short_description: Some(message(check.title.clone())),
+                        help: Some(message(check.remediation.clone())),
+                    },
+                );
+                results.extend(check.findings.iter().map(finding_result));
+            }
+        }
+        TopLevelResponse::NoResponse(result) => {
+            notifications.push(Notification {
+                level: level(Severity::Error),
+                message: message(result.error.clone()),
+            });
+        }
+        _ => {}
+    }
+
+    let exit_code = response.exit_code();
+    return SarifLog {
+        version: SARIF_VERSION.to_string(),
+        schema: SARIF_SCHEMA.to_string(),
+        runs: vec![Run {
+            tool: Tool {
+                driver: ToolComponent {
+                    name: "groupls".to_string(),
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                    information_uri: INFORMATION_URI.to_string(),
+                    rules: rules,
+                },
+            },
+            invocations: vec![Invocation {
+                // Findings are results of a successful run, even though they fail the exit status
+                execution_successful: !matches!(response, TopLevelResponse::NoResponse(_)),
+                exit_code: exit_code,
+                tool_execution_notifications: notifications,
+            }],
+            results: results,
+        }],
+    };
+}
@@ -0,0 +1,258 @@
+// Contains logic for reading systemd userdb drop-in directories, which define
+// users and groups as JSON records (see nss-systemd(8) and userdbd(8))
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_derive::Deserialize;
+
+use crate::errors::{invalid_system_state, io_error, Error, Input};
+use crate::records::{GroupEntry, PasswdEntry, Provenance, SkippedLine};
+
+// Directories searched for drop-ins, highest precedence first
+pub const USERDB_DIRECTORIES: [&'static str; 5] = [
+    "/etc/userdb",
+    "/run/userdb",
+    "/run/host/userdb",
+    "/usr/local/lib/userdb",
+    "/usr/lib/userdb",
+];
+
+const DEFAULT_SHELL: &'static str = "/bin/bash";
+
+// A `*.user` record; only the fields that groupls uses are read
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UserRecord {
+    user_name: String,
+    uid: Option<i64>,
+    gid: Option<i64>,
+    real_name: Option<String>,
+    home_directory: Option<String>,
+    shell: Option<String>,
+    #[serde(default)]
+    member_of: Vec<String>,
+}
+
+// A `*.group` record; only the fields that groupls uses are read
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GroupRecord {
+    group_name: String,
+    gid: Option<i64>,
+    #[serde(default)]
+    members: Vec<String>,
+    #[serde(default)]
+    administrators: Vec<String>,
+}
+
+// The contents of one or more userdb directories
+#[derive(Clone)]
+pub struct UserdbRecords {
+    pub users: Vec<PasswdEntry>,
+    pub groups: Vec<GroupEntry>,
+    // (user name, group name) pairs from `memberOf` fields and `*.membership` drop-ins,
+    // along with the drop-in that listed them
+    pub memberships: Vec<(String, String, Provenance)>,
+    // Records that could not be read or parsed. The directories are read once for users
+    // and groups alike, so the two kinds are kept apart to report each with its own input.
+    pub skipped_users: Vec<SkippedLine>,
+    pub skipped_groups: Vec<SkippedLine>,
+}
+
+fn parse_user_record(
+    contents: &str,
+    provenance: Provenance,
+) -> Result<(PasswdEntry, Vec<String>), Error> {
+    let record: UserRecord = serde_json::from_str(contents)
+        .map_err(|_| invalid_system_state("user record", &provenance))?;
+    let user_id = record
+        .uid
+        .ok_or(invalid_system_state("user ID number", &provenance))?;
+
+    let entry = PasswdEntry {
+        // Users without an explicit GID get a group of their own with the same number
+        primary_group_id: record.gid.unwrap_or(user_id),
+        gecos: record.real_name.unwrap_or_default(),
+        home_directory: record
+            .home_directory
+            .unwrap_or(format!("/home/{}", record.user_name)),
+        shell: record.shell.unwrap_or(DEFAULT_SHELL.to_string()),
+        user: record.user_name,
+        user_id: user_id,
+        provenance: provenance,
+    };
+
+    return Ok((entry, record.member_of));
+}
+
+fn parse_group_record(contents: &str, provenance: Provenance) -> Result<GroupEntry, Error> {
+    let record: GroupRecord = serde_json::from_str(contents)
+        .map_err(|_| invalid_system_state("group record", &provenance))?;
+
+    return Ok(GroupEntry {
+        group_id: record
+            .gid
+            .ok_or(invalid_system_state("group ID number", &provenance))?,
+        group: record.group_name,
+        usernames: record.members,
+        administrators: record.administrators,
+        provenance: provenance,
+        member_provenance: vec![],
+    });
+}
+
+// Reads every drop-in from `directories`, in order of precedence. Directories that
+// do not exist are skipped; the ones that were read are returned alongside the records.
+// Leaves out the `privileged` and `secret` sections of a record (which hold password hashes
+// and the like), so that a skipped record can be reported without publishing them. A record
+// that is not even JSON is not reported at all, as there is no telling what it holds.
+fn redact_record(contents: &str) -> String {
+    match serde_json::from_str::<serde_json::Value>(contents) {
+        Ok(serde_json::Value::Object(mut record)) => {
+            record.remove("privileged");
+            record.remove("secret");
+            return serde_json::Value::Object(record).to_string();
+        }
+        Ok(other) => return other.to_string(),
+        Err(_) => return "[redacted]".to_string(),
+    }
+}
+
+pub fn read_directories(directories: &[PathBuf]) -> (UserdbRecords, Vec<String>) {
+    let mut records = UserdbRecords {
+        users: vec![],
+        groups: vec![],
+        memberships: vec![],
+        skipped_users: vec![],
+        skipped_groups: vec![],
+    };
+    let mut directories_read = vec![];
+
+    for directory in directories {
+        let mut paths: Vec<PathBuf> = match fs::read_dir(directory) {
+            Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+            Err(_) => continue,
+        };
+        paths.sort();
+        directories_read.push(directory.display().to_string());
+
+        for path in paths {
+            read_drop_in(&path, &mut records);
+        }
+    }
+
+    return (records, directories_read);
+}
+
+fn read_drop_in(path: &Path, records: &mut UserdbRecords) {
+    let file_name = match path.file_name().and_then(|name| name.to_str()) {
+        Some(file_name) => file_name,
+        None => return,
+    };
+
+    let provenance = Provenance {
+        file: path.display().to_string(),
+        line: None,
+    };
+
+    if let Some(membership) = file_name.strip_suffix(".membership") {
+        if let Some((user_name, group_name)) = membership.split_once(':') {
+            records
+                .memberships
+                .push((user_name.to_string(), group_name.to_string(), provenance));
+        }
+        return;
+    }
+
+    let is_user = file_name.ends_with(".user");
+    if !is_user && !file_name.ends_with(".group") {
+        return;
+    }
+
+    // UID and GID symlinks (e.g. `1000.user`) point at the named records
+    let stem = &file_name[..file_name.rfind('.').expect("Logic error")];
+    if stem.chars().all(|c| c.is_ascii_digit()) {
+        return;
+    }
+
+    let input = if is_user { Input::Users } else { Input::Groups };
+    let mut text = String::new();
+    let result = fs::read_to_string(path)
+        .map_err(|e| io_error(input, path.display().to_string(), e))
+        .and_then(|contents| {
+            text = redact_record(&contents);
+            if is_user {
+                let (user, member_of) = parse_user_record(&contents, provenance.clone())?;
+                if !records.users.iter().any(|u| u.user == user.user) {
+                    for group_name in member_of {
+                        records.memberships.push((
+                            user.user.clone(),
+                            group_name,
+                            provenance.clone(),
+                        ));
+                    }
+                    records.users.push(user);
+                }
+            } else {
+                let group = parse_group_record(&contents, provenance.clone())?;
+                if !records.groups.iter().any(|g| g.group == group.group) {
+                    records.groups.push(group);
+                }
+            }
+            Ok(())
+        });
+
+    if let Err(error) = result {
+        let skipped = SkippedLine {
+            provenance: provenance,
+            text: text,
+            reason: error.reason(),
+        };
+        if is_user {
+            records.skipped_users.push(skipped);
+        } else {
+            records.skipped_groups.push(skipped);
+        }
+    }
+}
+
+// Adds userdb users that are not already defined by the flat files
+pub fn merge_users(users: &mut Vec<PasswdEntry>, records: &UserdbRecords) {
+    for user in records.users.iter() {
+        if !users.iter().any(|u| u.user == user.user) {
+            users.push(user.clone());
+        }
+    }
+}
+
+// Adds userdb groups that are not already defined by the flat files, then
+// applies memberships to whichever group (flat or userdb) carries the name
+pub fn merge_groups(groups: &mut Vec<GroupEntry>, records: &UserdbRecords) {
+    for group in records.groups.iter() {
+        match groups.iter_mut().find(|g| g.group == group.group) {
+            Some(existing) => {
+                for username in group.usernames.iter() {
+                    if !existing.usernames.contains(username) {
+                        existing
+                            .member_provenance
+                            .push((username.clone(), group.provenance.clone()));
+                        existing.usernames.push(username.clone());
+                    }
+                }
+            }
+            None => groups.push(group.clone()),
+        }
+    }
+
+    for (user_name, group_name, provenance) in records.memberships.iter() {
+        if let Some(group) = groups.iter_mut().find(|g| g.group == *group_name) {
+            if !group.usernames.contains(user_name) {
+                group
+                    .member_provenance
+                    .push((user_name.clone(), provenance.clone()));
+                group.usernames.push(user_name.clone());
+            }
+        }
+    }
+}